@@ -0,0 +1,4764 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::{error, fmt, fs, io, iter, result, str};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref RULE_REGEX: Regex = Regex::new(r"^(?P<color>(\w+\s?)+) bags contain (?P<contents>.+)$").expect("illegal regex");
+    static ref CONTENT_REGEX: Regex = Regex::new(r"^\s?(?P<count>\d+)\s(?P<color>(\w+\s?)+)\sbags?\.?").expect("illegal regex");
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Content {
+    pub count: u64,
+    pub color: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Bag {
+    pub color: String,
+    pub contents: Vec<Content>,
+    // The line the bag was parsed from, only kept when Config::preserve_raw is set
+    pub raw: Option<String>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum ParseError {
+    InvalidRule,
+    InvalidContent(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidRule => write!(f, "invalid rule"),
+            ParseError::InvalidContent(content) => write!(f, "invalid contents {:?}", content),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+impl fmt::Display for Content {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let noun = if self.count == 1 { "bag" } else { "bags" };
+        write!(f, "{} {} {}", self.count, self.color, noun)
+    }
+}
+
+impl fmt::Display for Bag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} bags contain ", self.color)?;
+        if self.contents.is_empty() {
+            return write!(f, "no other bags.");
+        }
+        for (i, content) in self.contents.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", content)?;
+        }
+        write!(f, ".")
+    }
+}
+
+// A comment may only follow the period ending a rule, so a # anywhere earlier is left for
+// the parser to reject
+fn strip_comment(rule: &str) -> &str {
+    match rule.find('.') {
+        Some(end) if rule[end + 1..].trim_start().starts_with('#') => &rule[..=end],
+        _ => rule,
+    }
+}
+
+impl Content {
+    fn try_from_rule(rule: &str) -> result::Result<Option<Content>, ParseError> {
+        if rule.trim() == "no other bags." {
+            return Ok(None);
+        }
+        let invalid = || ParseError::InvalidContent(rule.trim().to_string());
+        let caps = CONTENT_REGEX.captures(rule).ok_or_else(invalid)?;
+        Ok(Some(Content {
+            count: caps["count"].parse::<u64>().map_err(|_| invalid())?,
+            color: caps["color"].to_string(),
+        }))
+    }
+}
+
+impl Bag {
+    pub fn new_from_rule(rule: &str) -> Bag {
+        Bag::try_from_rule(rule).expect("invalid rule")
+    }
+
+    pub fn try_from_rule(rule: &str) -> result::Result<Bag, ParseError> {
+        let rule = strip_comment(rule);
+        let caps = RULE_REGEX.captures(rule).ok_or(ParseError::InvalidRule)?;
+        let color = caps["color"].to_string();
+        let mut contents = Vec::new();
+        for rule in caps["contents"].split(',') {
+            if let Some(content) = Content::try_from_rule(rule)? {
+                contents.push(content);
+            }
+        }
+        Ok(Bag {
+            color,
+            contents,
+            raw: None,
+        })
+    }
+}
+
+pub fn to_bags(lines: impl Iterator<Item = result::Result<String, io::Error>>) -> Vec<Bag> {
+    let mut bags: Vec<Bag> = Vec::new();
+    for line in lines.flatten() {
+        bags.push(Bag::new_from_rule(&line))
+    }
+    bags
+}
+
+pub fn bags_to_contained_by_graph(bags: &[Bag]) -> HashMap<String, HashSet<String>> {
+    let mut graph = HashMap::new();
+    for bag in bags {
+        for contents in bag.contents.iter() {
+            if !graph.contains_key(&contents.color) {
+                graph.insert(contents.color.clone(), HashSet::new());
+            }
+            let contained_by = graph.get_mut(&contents.color).unwrap();
+            contained_by.insert(bag.color.clone());
+        }
+    }
+    graph
+}
+
+pub fn bags_to_contains_graph(bags: &[Bag]) -> HashMap<&str, HashSet<&Content>> {
+    let mut graph = HashMap::new();
+    for bag in bags {
+        let mut set = HashSet::new();
+        for contents in bag.contents.iter() {
+            set.insert(contents);
+        }
+        graph.insert(bag.color.as_str(), set);
+    }
+    graph
+}
+
+pub fn find_potential_containers(
+    color: &str,
+    graph: &HashMap<String, HashSet<String>>,
+) -> HashSet<String> {
+    let mut containers = HashSet::new();
+    _find_potential_containers(color, graph, &mut containers);
+    containers
+}
+
+fn _find_potential_containers(
+    color: &str,
+    graph: &HashMap<String, HashSet<String>>,
+    containers: &mut HashSet<String>,
+) {
+    if let Some(contained_by) = graph.get(color) {
+        for color in contained_by {
+            if !containers.contains(color) {
+                containers.insert(color.to_string());
+                _find_potential_containers(color, graph, containers);
+            }
+        }
+    }
+}
+
+pub fn find_bag_count(color: &str, graph: &HashMap<&str, HashSet<&Content>>) -> u64 {
+    // -1 because the outer bag doesn't count
+    _find_bag_count(color, graph) - 1u64
+}
+fn _find_bag_count(color: &str, graph: &HashMap<&str, HashSet<&Content>>) -> u64 {
+    let contents = graph.get(color).unwrap();
+    if !contents.is_empty() {
+        contents
+            .iter()
+            .map(|content| content.count * _find_bag_count(&content.color, graph))
+            .sum::<u64>()
+            + 1u64
+    } else {
+        1
+    }
+}
+
+pub fn index_colors(bags: &[Bag]) -> (Vec<String>, HashMap<String, usize>) {
+    let mut colors: Vec<String> = bags
+        .iter()
+        .flat_map(|bag| {
+            iter::once(&bag.color).chain(bag.contents.iter().map(|content| &content.color))
+        })
+        .cloned()
+        .collect::<HashSet<String>>()
+        .into_iter()
+        .collect();
+    colors.sort();
+    let index = colors
+        .iter()
+        .enumerate()
+        .map(|(i, color)| (color.clone(), i))
+        .collect();
+    (colors, index)
+}
+
+pub fn ancestors_at_distance(color: &str, distance: usize, bags: &[Bag]) -> HashSet<String> {
+    let graph = bags_to_contained_by_graph(bags);
+    let mut frontier: HashSet<String> = HashSet::new();
+    frontier.insert(color.to_string());
+    for _ in 0..distance {
+        frontier = frontier
+            .iter()
+            .filter_map(|color| graph.get(color))
+            .flatten()
+            .cloned()
+            .collect();
+    }
+    frontier
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Part {
+    One,
+    Two,
+    Both,
+}
+
+impl str::FromStr for Part {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Part, String> {
+        match s {
+            "1" => Ok(Part::One),
+            "2" => Ok(Part::Two),
+            _ => Err(format!("invalid part {:?}, expected 1 or 2", s)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Answers {
+    pub containers: Option<usize>,
+    pub bag_count: Option<u64>,
+}
+
+// Only the graph needed by the requested part is built
+pub fn solve_part(color: &str, bags: &[Bag], part: Part) -> Answers {
+    let mut answers = Answers::default();
+    if part != Part::Two {
+        let graph = bags_to_contained_by_graph(bags);
+        answers.containers = Some(find_potential_containers(color, &graph).len());
+    }
+    if part != Part::One {
+        let graph = bags_to_contains_graph(bags);
+        answers.bag_count = Some(find_bag_count(color, &graph));
+    }
+    answers
+}
+
+pub fn write_answers<W: io::Write>(answers: &Answers, out: &mut W) -> io::Result<()> {
+    if let Some(containers) = answers.containers {
+        writeln!(out, "The number of potential containers is {}", containers)?;
+    }
+    if let Some(count) = answers.bag_count {
+        writeln!(out, "You have to buy {} bags", count)?;
+    }
+    Ok(())
+}
+
+pub fn root_bags(bags: &[Bag]) -> HashSet<String> {
+    let contained: HashSet<&str> = bags
+        .iter()
+        .flat_map(|bag| bag.contents.iter().map(|content| content.color.as_str()))
+        .collect();
+    bags.iter()
+        .filter(|bag| !contained.contains(bag.color.as_str()))
+        .map(|bag| bag.color.clone())
+        .collect()
+}
+
+// The number of containment steps on the longest path from color down to a leaf
+pub fn max_depth(color: &str, graph: &HashMap<&str, HashSet<&Content>>) -> u64 {
+    graph
+        .get(color)
+        .and_then(|contents| {
+            contents
+                .iter()
+                .map(|content| max_depth(&content.color, graph) + 1)
+                .max()
+        })
+        .unwrap_or(0)
+}
+
+pub fn ruleset_height(bags: &[Bag]) -> u64 {
+    let graph = bags_to_contains_graph(bags);
+    root_bags(bags)
+        .iter()
+        .map(|root| max_depth(root, &graph))
+        .max()
+        .unwrap_or(0)
+}
+
+pub fn find_descendants(color: &str, graph: &HashMap<&str, HashSet<&Content>>) -> HashSet<String> {
+    let mut descendants = HashSet::new();
+    _find_descendants(color, graph, &mut descendants);
+    descendants
+}
+
+fn _find_descendants(
+    color: &str,
+    graph: &HashMap<&str, HashSet<&Content>>,
+    descendants: &mut HashSet<String>,
+) {
+    if let Some(contents) = graph.get(color) {
+        for content in contents {
+            if !descendants.contains(&content.color) {
+                descendants.insert(content.color.clone());
+                _find_descendants(&content.color, graph, descendants);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct TooMany {
+    pub limit: usize,
+}
+
+impl fmt::Display for TooMany {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "transitive closure has more than {} pairs", self.limit)
+    }
+}
+
+impl error::Error for TooMany {}
+
+// Gives up as soon as there are more than limit pairs, since the closure of a big ruleset can
+// take a lot of memory
+pub fn all_reachable_pairs(
+    bags: &[Bag],
+    limit: usize,
+) -> result::Result<Vec<(String, String)>, TooMany> {
+    let graph = bags_to_contains_graph(bags);
+    let mut pairs = Vec::new();
+    for bag in bags {
+        for descendant in find_descendants(&bag.color, &graph) {
+            if pairs.len() == limit {
+                return Err(TooMany { limit });
+            }
+            pairs.push((bag.color.clone(), descendant));
+        }
+    }
+    pairs.sort();
+    Ok(pairs)
+}
+
+pub struct Graph {
+    contains: HashMap<String, Vec<Content>>,
+    contained_by: HashMap<String, HashSet<String>>,
+}
+
+impl Graph {
+    pub fn new(bags: &[Bag]) -> Graph {
+        let contains = bags
+            .iter()
+            .map(|bag| (bag.color.clone(), bag.contents.clone()))
+            .collect();
+        Graph {
+            contains,
+            contained_by: bags_to_contained_by_graph(bags),
+        }
+    }
+
+    // Same as new, for bags that arrive one at a time rather than as a slice
+    pub fn from_bags<I: IntoIterator<Item = Bag>>(bags: I) -> Graph {
+        let mut graph = Graph {
+            contains: HashMap::new(),
+            contained_by: HashMap::new(),
+        };
+        for bag in bags {
+            for content in bag.contents.iter() {
+                graph
+                    .contained_by
+                    .entry(content.color.clone())
+                    .or_default()
+                    .insert(bag.color.clone());
+            }
+            graph.contains.insert(bag.color, bag.contents);
+        }
+        graph
+    }
+
+    // Rules come back sorted by color since the graph doesn't keep the input order
+    pub fn into_bags(self) -> Vec<Bag> {
+        let bags = self
+            .contains
+            .into_iter()
+            .map(|(color, contents)| Bag {
+                color,
+                contents,
+                raw: None,
+            })
+            .collect();
+        bags_sorted(bags)
+    }
+
+    // True if the color has a rule of its own, which find_bag_count needs to not panic
+    pub fn contains_color(&self, color: &str) -> bool {
+        self.contains.contains_key(color)
+    }
+
+    // Colors without a rule of their own are treated as empty
+    fn contents(&self, color: &str) -> &[Content] {
+        self.contains
+            .get(color)
+            .map_or(&[], |contents| contents.as_slice())
+    }
+
+    // The same shape bags_to_contains_graph builds, for the helpers that work on that
+    fn contains_graph(&self) -> HashMap<&str, HashSet<&Content>> {
+        self.contains
+            .iter()
+            .map(|(color, contents)| (color.as_str(), contents.iter().collect()))
+            .collect()
+    }
+
+    pub fn ancestors(&self, color: &str) -> HashSet<String> {
+        find_potential_containers(color, &self.contained_by)
+    }
+
+    pub fn descendants(&self, color: &str) -> HashSet<String> {
+        let mut descendants = HashSet::new();
+        let mut stack = vec![color];
+        while let Some(color) = stack.pop() {
+            for content in self.contents(color) {
+                if descendants.insert(content.color.clone()) {
+                    stack.push(&content.color);
+                }
+            }
+        }
+        descendants
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub sort_contents: bool,
+    pub preserve_raw: bool,
+}
+
+impl Config {
+    fn apply(&self, bag: &mut Bag, line: &str) {
+        if self.preserve_raw {
+            bag.raw = Some(line.to_string());
+        }
+        if self.sort_contents {
+            bag.contents
+                .sort_by(|a, b| a.color.cmp(&b.color).then(a.count.cmp(&b.count)));
+        }
+    }
+}
+
+// Blank lines are ignored, every other line either parses or is reported with its 1-based
+// line number
+pub fn parse_rules(input: &str) -> (Vec<Bag>, Vec<(usize, ParseError)>) {
+    parse_rules_with_config(input, &Config::default())
+}
+
+pub fn parse_rules_with_config(
+    input: &str,
+    config: &Config,
+) -> (Vec<Bag>, Vec<(usize, ParseError)>) {
+    let mut bags = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match Bag::try_from_rule(line) {
+            Ok(mut bag) => {
+                config.apply(&mut bag, line);
+                bags.push(bag)
+            }
+            Err(err) => errors.push((i + 1, err)),
+        }
+    }
+    (bags, errors)
+}
+
+// total_lines counts every line of the input, blank ones included, and skipped is the blank
+// lines plus the ones that failed to parse. Blank lines aren't held against success_rate,
+// which is the share of non-blank lines that parsed
+#[derive(Debug, PartialEq)]
+pub struct ParseReport {
+    pub total_lines: usize,
+    pub parsed: usize,
+    pub skipped: usize,
+    pub success_rate: f64,
+}
+
+pub fn parse_report(input: &str) -> ParseReport {
+    let (bags, errors) = parse_rules(input);
+    let total_lines = input.lines().count();
+    let rule_lines = bags.len() + errors.len();
+    // An empty input has nothing that failed to parse
+    let success_rate = if rule_lines == 0 {
+        1.0
+    } else {
+        bags.len() as f64 / rule_lines as f64
+    };
+    ParseReport {
+        total_lines,
+        parsed: bags.len(),
+        skipped: total_lines - bags.len(),
+        success_rate,
+    }
+}
+
+// Each reachable color keeps only the parent it was first reached through in a breadth
+// first walk from color
+pub fn spanning_tree(color: &str, graph: &Graph) -> Vec<(String, String)> {
+    let mut edges = Vec::new();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(color.to_string());
+    queue.push_back(color.to_string());
+    while let Some(parent) = queue.pop_front() {
+        for content in graph.contents(&parent) {
+            if seen.insert(content.color.clone()) {
+                edges.push((parent.clone(), content.color.clone()));
+                queue.push_back(content.color.clone());
+            }
+        }
+    }
+    edges
+}
+
+// None if the count doesn't fit in a u64
+pub fn checked_bag_count(color: &str, graph: &Graph) -> Option<u64> {
+    // -1 because the outer bag doesn't count
+    _checked_bag_count(color, graph).map(|count| count - 1)
+}
+
+fn _checked_bag_count(color: &str, graph: &Graph) -> Option<u64> {
+    graph
+        .contents(color)
+        .iter()
+        .try_fold(1u64, |total, content| {
+            let inner = _checked_bag_count(&content.color, graph)?;
+            total.checked_add(content.count.checked_mul(inner)?)
+        })
+}
+
+pub fn find_bag_count_scaled(color: &str, k: u64, graph: &Graph) -> u64 {
+    checked_bag_count(color, graph)
+        .and_then(|count| count.checked_mul(k))
+        .expect("bag count overflowed")
+}
+
+fn canonical_color(color: &str) -> String {
+    let mut words: Vec<String> = color
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect();
+    while words.len() > 1 && matches!(words.last().map(String::as_str), Some("bag") | Some("bags"))
+    {
+        words.pop();
+    }
+    words.join(" ")
+}
+
+// Colors are trimmed, lowercased and stripped of any trailing "bag"/"bags" left over from a
+// bad parse. Contents of a bag that collapse to the same color have their counts summed,
+// while a repeated rule only adds contents the first rule for that color didn't have.
+pub fn canonicalize_colors(bags: &mut Vec<Bag>) {
+    let mut merged: Vec<Bag> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for bag in bags.drain(..) {
+        let mut contents: Vec<Content> = Vec::new();
+        for content in bag.contents {
+            let color = canonical_color(&content.color);
+            match contents.iter_mut().find(|existing| existing.color == color) {
+                Some(existing) => existing.count += content.count,
+                None => contents.push(Content {
+                    count: content.count,
+                    color,
+                }),
+            }
+        }
+        let color = canonical_color(&bag.color);
+        match positions.get(&color) {
+            Some(&i) => {
+                for content in contents {
+                    if !merged[i].contents.iter().any(|c| c.color == content.color) {
+                        merged[i].contents.push(content);
+                    }
+                }
+            }
+            None => {
+                positions.insert(color.clone(), merged.len());
+                merged.push(Bag {
+                    color,
+                    contents,
+                    raw: bag.raw,
+                });
+            }
+        }
+    }
+    *bags = merged;
+}
+
+pub fn degrees(color: &str, bags: &[Bag]) -> (usize, usize) {
+    let parents = bags_to_contained_by_graph(bags)
+        .get(color)
+        .map_or(0, |contained_by| contained_by.len());
+    let children = bags_to_contains_graph(bags)
+        .get(color)
+        .map_or(0, |contents| {
+            contents
+                .iter()
+                .map(|content| &content.color)
+                .collect::<HashSet<_>>()
+                .len()
+        });
+    (parents, children)
+}
+
+// Bags more than max_depth levels below color are counted but not opened
+pub fn find_bag_count_depth_limited(color: &str, max_depth: usize, graph: &Graph) -> u64 {
+    // -1 because the outer bag doesn't count
+    _find_bag_count_depth_limited(color, max_depth, graph) - 1
+}
+
+fn _find_bag_count_depth_limited(color: &str, max_depth: usize, graph: &Graph) -> u64 {
+    if max_depth == 0 {
+        return 1;
+    }
+    graph
+        .contents(color)
+        .iter()
+        .map(|content| {
+            content.count * _find_bag_count_depth_limited(&content.color, max_depth - 1, graph)
+        })
+        .sum::<u64>()
+        + 1
+}
+
+pub fn shortest_path(from: &str, to: &str, graph: &Graph) -> Option<Vec<String>> {
+    let mut parents: HashMap<String, String> = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(from.to_string());
+    while let Some(color) = queue.pop_front() {
+        if color == to {
+            let mut path = vec![color];
+            while let Some(parent) = parents.get(path.last().unwrap()) {
+                path.push(parent.clone());
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for content in graph.contents(&color) {
+            if content.color != from && !parents.contains_key(&content.color) {
+                parents.insert(content.color.clone(), color.clone());
+                queue.push_back(content.color.clone());
+            }
+        }
+    }
+    None
+}
+
+// Where the to in "<from> to <to>" is, as long as there's a color on both sides of it
+fn path_separator(words: &[&str]) -> Option<usize> {
+    let separator = 1 + words.iter().skip(1).position(|&word| word == "to")?;
+    if separator + 1 < words.len() {
+        Some(separator)
+    } else {
+        None
+    }
+}
+
+// Reads one command per line until quit or EOF:
+//   containers <color>
+//   count <color>
+//   path <from> to <to>
+pub fn run_repl<R: io::BufRead, W: io::Write>(
+    bags: &[Bag],
+    input: R,
+    out: &mut W,
+) -> io::Result<()> {
+    let graph = Graph::new(bags);
+    let contained_by = bags_to_contained_by_graph(bags);
+    for line in input.lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.split_first() {
+            None => continue,
+            Some((&"quit", [])) => break,
+            Some((&"containers", color)) if !color.is_empty() => {
+                let color = color.join(" ");
+                if graph.contains_color(&color) || contained_by.contains_key(&color) {
+                    let containers = find_potential_containers(&color, &contained_by);
+                    writeln!(out, "{}", containers.len())?;
+                } else {
+                    writeln!(out, "unknown color {}", color)?;
+                }
+            }
+            Some((&"count", color)) if !color.is_empty() => {
+                let color = color.join(" ");
+                if graph.contains_color(&color) {
+                    match checked_bag_count(&color, &graph) {
+                        Some(count) => writeln!(out, "{}", count)?,
+                        None => writeln!(out, "count overflowed")?,
+                    }
+                } else {
+                    writeln!(out, "unknown color {}", color)?;
+                }
+            }
+            Some((&"path", colors)) if path_separator(colors).is_some() => {
+                let separator = path_separator(colors).unwrap();
+                let from = colors[..separator].join(" ");
+                let to = colors[separator + 1..].join(" ");
+                match shortest_path(&from, &to, &graph) {
+                    Some(path) => writeln!(out, "{}", path.join(" -> "))?,
+                    None => writeln!(out, "no path from {} to {}", from, to)?,
+                }
+            }
+            _ => writeln!(out, "unknown command {:?}", line.trim())?,
+        }
+    }
+    Ok(())
+}
+
+pub fn is_forest(bags: &[Bag]) -> bool {
+    bags_to_contained_by_graph(bags)
+        .values()
+        .all(|contained_by| contained_by.len() <= 1)
+}
+
+// Colors that can both hold color and be found inside it, which only happens with cycles
+pub fn part1_part2_overlap(color: &str, graph: &Graph) -> HashSet<String> {
+    let descendants = graph.descendants(color);
+    graph
+        .ancestors(color)
+        .into_iter()
+        .filter(|ancestor| descendants.contains(ancestor))
+        .collect()
+}
+
+fn shell_quote(value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_.-".contains(c);
+    if !value.is_empty() && value.chars().all(safe) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+// KEY=value lines meant for `eval` in a POSIX shell
+pub fn write_env<W: io::Write>(answers: &Answers, out: &mut W) -> io::Result<()> {
+    if let Some(containers) = answers.containers {
+        writeln!(out, "CONTAINERS={}", shell_quote(&containers.to_string()))?;
+    }
+    if let Some(count) = answers.bag_count {
+        writeln!(out, "BAG_COUNT={}", shell_quote(&count.to_string()))?;
+    }
+    Ok(())
+}
+
+// Ratio of the largest to the smallest child contribution, where a child contributes its
+// count times the bags it holds plus itself. Children with a count of 0 contribute nothing
+// and are skipped rather than making the ratio infinite, so a bag with no nonzero children
+// has no balance, the same as an empty one
+pub fn child_balance(color: &str, graph: &Graph) -> Option<f64> {
+    let contributions: Vec<u64> = graph
+        .contents(color)
+        .iter()
+        .filter(|content| content.count > 0)
+        .map(|content| {
+            _checked_bag_count(&content.color, graph)
+                .and_then(|count| count.checked_mul(content.count))
+                .expect("bag count overflowed")
+        })
+        .collect();
+    let largest = *contributions.iter().max()?;
+    let smallest = *contributions.iter().min()?;
+    Some(largest as f64 / smallest as f64)
+}
+
+pub fn canonical_text(bags: &[Bag]) -> String {
+    let mut text = String::new();
+    for mut bag in bags_sorted(bags.to_vec()) {
+        bag.contents
+            .sort_by(|a, b| a.color.cmp(&b.color).then(a.count.cmp(&b.count)));
+        text.push_str(&bag.to_string());
+        text.push('\n');
+    }
+    text
+}
+
+// Leaves are colors with no contents, whether or not they have a rule of their own
+pub fn popular_leaves(bags: &[Bag], min_parents: usize) -> Vec<(String, usize)> {
+    let contains = bags_to_contains_graph(bags);
+    let mut leaves: Vec<(String, usize)> = bags_to_contained_by_graph(bags)
+        .into_iter()
+        .filter(|(color, _)| contains.get(color.as_str()).is_none_or(HashSet::is_empty))
+        .map(|(color, contained_by)| (color, contained_by.len()))
+        .filter(|(_, parents)| *parents >= min_parents)
+        .collect();
+    leaves.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    leaves
+}
+
+fn _postorder<'a>(
+    color: &'a str,
+    graph: &'a Graph,
+    seen: &mut HashSet<&'a str>,
+    order: &mut Vec<&'a str>,
+) {
+    seen.insert(color);
+    for content in graph.contents(color) {
+        if !seen.contains(content.color.as_str()) {
+            _postorder(&content.color, graph, seen, order);
+        }
+    }
+    order.push(color);
+}
+
+// Maps every color reachable from root, other than root itself, to its immediate dominator:
+// the closest bag that every path from root to that color has to pass through. Uses the
+// iterative algorithm from Cooper, Harvey and Kennedy's "A Simple, Fast Dominance Algorithm".
+pub fn dominators(root: &str, graph: &Graph) -> HashMap<String, String> {
+    let mut order = Vec::new();
+    _postorder(root, graph, &mut HashSet::new(), &mut order);
+    let position: HashMap<&str, usize> = order.iter().enumerate().map(|(i, &c)| (c, i)).collect();
+    let root_index = order.len() - 1;
+
+    let mut idom: Vec<Option<usize>> = vec![None; order.len()];
+    idom[root_index] = Some(root_index);
+    let intersect = |idom: &[Option<usize>], mut a: usize, mut b: usize| {
+        while a != b {
+            while a < b {
+                a = idom[a].unwrap();
+            }
+            while b < a {
+                b = idom[b].unwrap();
+            }
+        }
+        a
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in (0..root_index).rev() {
+            let preds = graph
+                .contained_by
+                .get(order[i])
+                .into_iter()
+                .flatten()
+                .filter_map(|parent| position.get(parent.as_str()))
+                .filter(|&&p| idom[p].is_some());
+            let new_idom = preds.fold(None, |acc, &p| match acc {
+                None => Some(p),
+                Some(a) => Some(intersect(&idom, a, p)),
+            });
+            if new_idom.is_some() && new_idom != idom[i] {
+                idom[i] = new_idom;
+                changed = true;
+            }
+        }
+    }
+
+    (0..root_index)
+        .filter_map(|i| idom[i].map(|d| (order[i].to_string(), order[d].to_string())))
+        .collect()
+}
+
+// How many copies of each color end up inside one bag of color
+pub fn content_multiset(color: &str, graph: &Graph) -> HashMap<String, u64> {
+    let mut multiset = HashMap::new();
+    for content in graph.contents(color) {
+        *multiset.entry(content.color.clone()).or_insert(0) += content.count;
+        for (inner, count) in content_multiset(&content.color, graph) {
+            *multiset.entry(inner).or_insert(0) += content.count * count;
+        }
+    }
+    multiset
+}
+
+// Each (outer, inner) query is answered from the multiset of outer, built once per outer
+pub fn count_of_batch(queries: &[(&str, &str)], graph: &Graph) -> Vec<u64> {
+    let mut cache: HashMap<&str, HashMap<String, u64>> = HashMap::new();
+    queries
+        .iter()
+        .map(|&(outer, inner)| {
+            let multiset = cache
+                .entry(outer)
+                .or_insert_with(|| content_multiset(outer, graph));
+            multiset.get(inner).copied().unwrap_or(0)
+        })
+        .collect()
+}
+
+pub fn leaf_edge_count(bags: &[Bag]) -> usize {
+    let graph = bags_to_contains_graph(bags);
+    let is_leaf = |color: &str| graph.get(color).is_none_or(HashSet::is_empty);
+    bags.iter()
+        .flat_map(|bag| {
+            bag.contents
+                .iter()
+                .filter(|content| is_leaf(&content.color))
+                .map(move |content| (&bag.color, &content.color))
+        })
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+pub fn all_potential_containers(bags: &[Bag]) -> HashMap<String, HashSet<String>> {
+    let graph = bags_to_contained_by_graph(bags);
+    let (colors, _) = index_colors(bags);
+    colors
+        .into_iter()
+        .map(|color| {
+            let containers = find_potential_containers(&color, &graph);
+            (color, containers)
+        })
+        .collect()
+}
+
+// Ties are broken by color name
+pub fn colors_by_container_count(bags: &[Bag]) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = all_potential_containers(bags)
+        .into_iter()
+        .map(|(color, containers)| (color, containers.len()))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum ParseWithinError {
+    Timeout { parsed: usize, elapsed: Duration },
+    Parse(usize, ParseError),
+}
+
+impl fmt::Display for ParseWithinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseWithinError::Timeout { parsed, elapsed } => write!(
+                f,
+                "parsing timed out after {:?} with {} rules parsed",
+                elapsed, parsed
+            ),
+            ParseWithinError::Parse(line, err) => write!(f, "line {}: {}", line, err),
+        }
+    }
+}
+
+impl error::Error for ParseWithinError {}
+
+const PARSE_DEADLINE_CHECK_INTERVAL: usize = 64;
+
+// Parses until elapsed reports a time past budget, which it's asked every
+// PARSE_DEADLINE_CHECK_INTERVAL lines
+fn _parse_within<F: FnMut() -> Duration>(
+    input: &str,
+    budget: Duration,
+    mut elapsed: F,
+) -> result::Result<Vec<Bag>, ParseWithinError> {
+    let mut bags = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        if i % PARSE_DEADLINE_CHECK_INTERVAL == 0 {
+            let elapsed = elapsed();
+            if elapsed > budget {
+                return Err(ParseWithinError::Timeout {
+                    parsed: bags.len(),
+                    elapsed,
+                });
+            }
+        }
+        if !line.trim().is_empty() {
+            let bag =
+                Bag::try_from_rule(line).map_err(|err| ParseWithinError::Parse(i + 1, err))?;
+            bags.push(bag);
+        }
+    }
+    Ok(bags)
+}
+
+// The clock is only checked every PARSE_DEADLINE_CHECK_INTERVAL lines, so the budget can be
+// overrun by the time it takes to parse that many rules. Stops at the first bad line
+pub fn parse_within(input: &str, budget: Duration) -> result::Result<Vec<Bag>, ParseWithinError> {
+    let start = Instant::now();
+    _parse_within(input, budget, || start.elapsed())
+}
+
+// A removed color cuts off a leaf exactly when it dominates that leaf, so this walks up the
+// dominator tree from every reachable leaf. Neither target nor the leaves themselves count.
+pub fn critical_colors(target: &str, graph: &Graph) -> Vec<String> {
+    let idoms = dominators(target, graph);
+    let mut critical = HashSet::new();
+    for color in graph.descendants(target) {
+        if !graph.contents(&color).is_empty() {
+            continue;
+        }
+        let mut current = &idoms[&color];
+        while current != target {
+            critical.insert(current.clone());
+            current = &idoms[current];
+        }
+    }
+    let mut critical: Vec<String> = critical.into_iter().collect();
+    critical.sort();
+    critical
+}
+
+pub fn total_weight(bags: &[Bag]) -> u64 {
+    bags.iter()
+        .flat_map(|bag| bag.contents.iter())
+        .try_fold(0u64, |total, content| total.checked_add(content.count))
+        .expect("total weight overflowed")
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    UnexpectedEof,
+    VarintTooLong,
+    InvalidUtf8,
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEof => write!(f, "unexpected end of input"),
+            DecodeError::VarintTooLong => write!(f, "varint does not fit in a u64"),
+            DecodeError::InvalidUtf8 => write!(f, "color is not valid utf-8"),
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after graph"),
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+// LEB128: seven bits per byte, least significant first, high bit set on all but the last
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn write_str(bytes: &mut Vec<u8>, s: &str) {
+    write_varint(bytes, s.len() as u64);
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn varint(&mut self) -> result::Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.bytes.split_first().ok_or(DecodeError::UnexpectedEof)?;
+            self.bytes = rest;
+            let bits = u64::from(byte & 0x7f);
+            if shift == 63 && bits > 1 {
+                return Err(DecodeError::VarintTooLong);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::VarintTooLong)
+    }
+
+    fn string(&mut self) -> result::Result<String, DecodeError> {
+        let len = self.varint()?;
+        if len > self.bytes.len() as u64 {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        let (s, rest) = self.bytes.split_at(len as usize);
+        self.bytes = rest;
+        String::from_utf8(s.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+    }
+}
+
+// A varint rule count, then per rule its length-prefixed color, a varint content count and
+// each content as a length-prefixed color followed by a varint count. Rules are written in
+// color order so equal graphs encode to equal bytes.
+pub fn to_bytes(graph: &Graph) -> Vec<u8> {
+    let mut colors: Vec<&String> = graph.contains.keys().collect();
+    colors.sort();
+    let mut bytes = Vec::new();
+    write_varint(&mut bytes, colors.len() as u64);
+    for color in colors {
+        let contents = graph.contents(color);
+        write_str(&mut bytes, color);
+        write_varint(&mut bytes, contents.len() as u64);
+        for content in contents {
+            write_str(&mut bytes, &content.color);
+            write_varint(&mut bytes, content.count);
+        }
+    }
+    bytes
+}
+
+pub fn from_bytes(bytes: &[u8]) -> result::Result<Graph, DecodeError> {
+    let mut decoder = Decoder { bytes };
+    let mut bags = Vec::new();
+    for _ in 0..decoder.varint()? {
+        let color = decoder.string()?;
+        let mut contents = Vec::new();
+        for _ in 0..decoder.varint()? {
+            let color = decoder.string()?;
+            let count = decoder.varint()?;
+            contents.push(Content { count, color });
+        }
+        bags.push(Bag {
+            color,
+            contents,
+            raw: None,
+        });
+    }
+    if !decoder.bytes.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(Graph::new(&bags))
+}
+
+// Ties are broken by color name
+pub fn descendant_counts_ranked(color: &str, graph: &Graph) -> Vec<(String, u64)> {
+    let mut ranked: Vec<(String, u64)> = content_multiset(color, graph).into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+pub fn is_self_contained(bags: &[Bag]) -> bool {
+    let defined: HashSet<&str> = bags.iter().map(|bag| bag.color.as_str()).collect();
+    bags.iter()
+        .flat_map(|bag| bag.contents.iter())
+        .all(|content| defined.contains(content.color.as_str()))
+}
+
+// color itself is only counted if it can be reached again through a cycle
+pub fn distinct_colors_within_depth(color: &str, depth: usize, graph: &Graph) -> usize {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut frontier = vec![color];
+    for _ in 0..depth {
+        let mut next = Vec::new();
+        for color in frontier {
+            for content in graph.contents(color) {
+                if seen.insert(&content.color) {
+                    next.push(content.color.as_str());
+                }
+            }
+        }
+        frontier = next;
+    }
+    seen.len()
+}
+
+fn dot_id(color: &str) -> String {
+    format!("\"{}\"", color.replace('\\', r"\\").replace('"', "\\\""))
+}
+
+// Edges point from a bag to each color it directly holds, labelled with the count
+pub fn to_dot(bags: &[Bag]) -> String {
+    let mut edges: Vec<(&str, &str, u64)> = bags
+        .iter()
+        .flat_map(|bag| {
+            bag.contents
+                .iter()
+                .map(move |content| (bag.color.as_str(), content.color.as_str(), content.count))
+        })
+        .collect();
+    edges.sort();
+    let mut dot = String::from("digraph contains {\n");
+    for (color, content, count) in edges {
+        dot.push_str(&format!(
+            "    {} -> {} [label={}];\n",
+            dot_id(color),
+            dot_id(content),
+            count
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+// Edges point from a color to each bag that can directly hold it
+pub fn to_dot_reverse(bags: &[Bag]) -> String {
+    let mut edges: Vec<(String, String)> = bags_to_contained_by_graph(bags)
+        .into_iter()
+        .flat_map(|(color, contained_by)| {
+            contained_by
+                .into_iter()
+                .map(move |container| (color.clone(), container))
+        })
+        .collect();
+    edges.sort();
+    let mut dot = String::from("digraph contained_by {\n");
+    for (color, container) in edges {
+        dot.push_str(&format!(
+            "    {} -> {};\n",
+            dot_id(&color),
+            dot_id(&container)
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+// parent and every bag that can hold it, or zero if parent doesn't directly contain child
+pub fn ancestors_affected(child: &str, parent: &str, graph: &Graph) -> usize {
+    if graph
+        .contents(parent)
+        .iter()
+        .any(|content| content.color == child)
+    {
+        graph.ancestors(parent).len() + 1
+    } else {
+        0
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Error {
+    Parse(usize, ParseError),
+    UnknownColor(String),
+    Overflow,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(line, err) => write!(f, "line {}: {}", line, err),
+            Error::UnknownColor(color) => write!(f, "no rule for {} bags", color),
+            Error::Overflow => write!(f, "bag count overflowed"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Solution {
+    pub containers: usize,
+    pub total_contents: u64,
+}
+
+pub fn solve(input: &str, target: &str) -> result::Result<Solution, Error> {
+    let (bags, errors) = parse_rules(input);
+    if let Some((line, err)) = errors.into_iter().next() {
+        return Err(Error::Parse(line, err));
+    }
+    let graph = Graph::new(&bags);
+    if !graph.contains_color(target) {
+        return Err(Error::UnknownColor(target.to_string()));
+    }
+    Ok(Solution {
+        containers: graph.ancestors(target).len(),
+        total_contents: checked_bag_count(target, &graph).ok_or(Error::Overflow)?,
+    })
+}
+
+pub fn top_containers(target: &str, bags: &[Bag]) -> HashSet<String> {
+    let roots = root_bags(bags);
+    find_potential_containers(target, &bags_to_contained_by_graph(bags))
+        .into_iter()
+        .filter(|container| roots.contains(container))
+        .collect()
+}
+
+// Yields every path from color down to a leaf in depth first order, along with how many of
+// that leaf the path accounts for. Summing the products gives the number of leaf bags inside
+// color, the intermediate bags on each path are part of find_bag_count but not of the products.
+pub fn flatten_with_paths<'a>(
+    color: &str,
+    graph: &'a Graph,
+) -> impl Iterator<Item = (Vec<String>, u64)> + 'a {
+    let mut stack: Vec<(Vec<String>, u64)> = graph
+        .contents(color)
+        .iter()
+        .rev()
+        .map(|content| {
+            (
+                vec![color.to_string(), content.color.clone()],
+                content.count,
+            )
+        })
+        .collect();
+    iter::from_fn(move || {
+        while let Some((path, product)) = stack.pop() {
+            let contents = graph.contents(path.last().unwrap());
+            if contents.is_empty() {
+                return Some((path, product));
+            }
+            for content in contents.iter().rev() {
+                let mut next = path.clone();
+                next.push(content.color.clone());
+                stack.push((next, product * content.count));
+            }
+        }
+        None
+    })
+}
+
+fn _subtree_shape<'a>(
+    color: &'a str,
+    graph: &HashMap<&'a str, HashSet<&'a Content>>,
+    shapes: &mut HashMap<Vec<(u64, usize)>, usize>,
+    memo: &mut HashMap<&'a str, usize>,
+) -> usize {
+    if let Some(&shape) = memo.get(color) {
+        return shape;
+    }
+    let mut children: Vec<(u64, usize)> = graph
+        .get(color)
+        .into_iter()
+        .flatten()
+        .map(|content| {
+            let shape = _subtree_shape(&content.color, graph, shapes, memo);
+            (content.count, shape)
+        })
+        .collect();
+    children.sort_unstable();
+    let next = shapes.len();
+    let shape = *shapes.entry(children).or_insert(next);
+    memo.insert(color, shape);
+    shape
+}
+
+// Two subtrees have the same shape when their children pair up with equal counts and equal
+// shapes, regardless of the colors involved. All leaves share one shape.
+pub fn unique_subtree_count(bags: &[Bag]) -> usize {
+    let graph = bags_to_contains_graph(bags);
+    let mut shapes = HashMap::new();
+    let mut memo = HashMap::new();
+    for bag in bags {
+        _subtree_shape(&bag.color, &graph, &mut shapes, &mut memo);
+    }
+    shapes.len()
+}
+
+// Calls cb with each target's container count and bag count as soon as it's solved
+pub fn solve_each<F: FnMut(&str, usize, u64)>(targets: &[&str], bags: &[Bag], mut cb: F) {
+    let graph = Graph::new(bags);
+    for &target in targets {
+        let containers = graph.ancestors(target).len();
+        let count = checked_bag_count(target, &graph).expect("bag count overflowed");
+        cb(target, containers, count);
+    }
+}
+
+// Counts color itself, so a leaf gives 1. Zero means no leaf can be reached at all, which only
+// happens when every path from color ends in a cycle.
+pub fn min_colors_to_leaf(color: &str, graph: &Graph) -> usize {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(color);
+    queue.push_back((color, 1));
+    while let Some((color, length)) = queue.pop_front() {
+        let contents = graph.contents(color);
+        if contents.is_empty() {
+            return length;
+        }
+        for content in contents {
+            if seen.insert(&content.color) {
+                queue.push_back((&content.color, length + 1));
+            }
+        }
+    }
+    0
+}
+
+// Returns (roots, internal, leaves), each sorted. A color that nothing holds is a root even
+// if it's also empty, so isolated colors show up as roots and never as leaves.
+pub fn classify_bags(bags: &[Bag]) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let contained_by = bags_to_contained_by_graph(bags);
+    let contains = bags_to_contains_graph(bags);
+    let (colors, _) = index_colors(bags);
+    let mut roots = Vec::new();
+    let mut internal = Vec::new();
+    let mut leaves = Vec::new();
+    for color in colors {
+        if !contained_by.contains_key(&color) {
+            roots.push(color);
+        } else if contains.get(color.as_str()).is_none_or(HashSet::is_empty) {
+            leaves.push(color);
+        } else {
+            internal.push(color);
+        }
+    }
+    (roots, internal, leaves)
+}
+
+// Repeated (parent, child) pairs, from duplicate rules or contents, have their counts summed
+pub fn aggregate_edges(bags: &[Bag]) -> HashMap<(String, String), u64> {
+    let mut edges = HashMap::new();
+    for bag in bags {
+        for content in bag.contents.iter() {
+            *edges
+                .entry((bag.color.clone(), content.color.clone()))
+                .or_insert(0) += content.count;
+        }
+    }
+    edges
+}
+
+// The longest of the shortest paths between any color and the colors it can reach, found with
+// a breadth first search from every color
+pub fn diameter(bags: &[Bag]) -> u64 {
+    let graph = Graph::new(bags);
+    let (colors, _) = index_colors(bags);
+    let mut longest = 0;
+    for color in colors.iter() {
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(color);
+        queue.push_back((color.as_str(), 0));
+        while let Some((color, distance)) = queue.pop_front() {
+            longest = longest.max(distance);
+            for content in graph.contents(color) {
+                if seen.insert(&content.color) {
+                    queue.push_back((&content.color, distance + 1));
+                }
+            }
+        }
+    }
+    longest
+}
+
+// (a, b) where a directly holds at least as many of every color b holds, and more of
+// something. Empty bags are left out as every other bag would trivially extend them.
+pub fn superset_pairs(bags: &[Bag]) -> Vec<(String, String)> {
+    let multisets: Vec<(&str, HashMap<&str, u64>)> = bags
+        .iter()
+        .map(|bag| {
+            let mut multiset = HashMap::new();
+            for content in bag.contents.iter() {
+                *multiset.entry(content.color.as_str()).or_insert(0) += content.count;
+            }
+            (bag.color.as_str(), multiset)
+        })
+        .collect();
+    let mut pairs = Vec::new();
+    for (a, a_contents) in multisets.iter() {
+        for (b, b_contents) in multisets.iter() {
+            if a == b || b_contents.is_empty() || a_contents == b_contents {
+                continue;
+            }
+            let contains_all = b_contents
+                .iter()
+                .all(|(color, count)| a_contents.get(color).is_some_and(|n| n >= count));
+            if contains_all {
+                pairs.push((a.to_string(), b.to_string()));
+            }
+        }
+    }
+    pairs.sort();
+    pairs
+}
+
+// score = descendants * ancestors / (n - 1)^2, with n the number of colors. A color can reach
+// and be reached by at most n - 1 others, so scores fall between 0 and 1, and anything that's
+// a root or a leaf scores 0.
+pub fn centrality(bags: &[Bag]) -> HashMap<String, f64> {
+    let graph = Graph::new(bags);
+    let (colors, _) = index_colors(bags);
+    let others = colors.len().saturating_sub(1).max(1) as f64;
+    colors
+        .into_iter()
+        .map(|color| {
+            let reach = graph.descendants(&color).len() as f64;
+            let reached_by = graph.ancestors(&color).len() as f64;
+            let score = reach * reached_by / (others * others);
+            (color, score)
+        })
+        .collect()
+}
+
+type Signature = (usize, Vec<(u64, usize)>, Vec<(u64, usize)>);
+
+struct Refinement {
+    children: Vec<Vec<(u64, usize)>>,
+    parents: Vec<Vec<(u64, usize)>>,
+    labels: Vec<usize>,
+}
+
+impl Refinement {
+    fn new(bags: &[Bag]) -> Refinement {
+        let (colors, index) = index_colors(bags);
+        let mut children = vec![Vec::new(); colors.len()];
+        let mut parents = vec![Vec::new(); colors.len()];
+        for ((parent, child), count) in aggregate_edges(bags) {
+            children[index[&parent]].push((count, index[&child]));
+            parents[index[&child]].push((count, index[&parent]));
+        }
+        Refinement {
+            children,
+            parents,
+            labels: vec![0; colors.len()],
+        }
+    }
+
+    fn signature(&self, node: usize) -> Signature {
+        let relabel = |edges: &[(u64, usize)]| {
+            let mut edges: Vec<(u64, usize)> = edges
+                .iter()
+                .map(|&(count, other)| (count, self.labels[other]))
+                .collect();
+            edges.sort_unstable();
+            edges
+        };
+        (
+            self.labels[node],
+            relabel(&self.children[node]),
+            relabel(&self.parents[node]),
+        )
+    }
+
+    fn refine(&mut self, interned: &mut HashMap<Signature, usize>) {
+        let labels: Vec<usize> = (0..self.labels.len())
+            .map(|node| {
+                let next = interned.len();
+                *interned.entry(self.signature(node)).or_insert(next)
+            })
+            .collect();
+        self.labels = labels;
+    }
+
+    // Like refine, but labels are hashes of the signatures rather than indices handed out in
+    // color order, so they come out the same whatever the colors are called
+    fn refine_hashed(&mut self) {
+        let labels: Vec<usize> = (0..self.labels.len())
+            .map(|node| {
+                let (label, children, parents) = self.signature(node);
+                let mut hasher = Fnv1a::new();
+                hasher.write_u64(label as u64);
+                for edges in &[children, parents] {
+                    hasher.write_u64(edges.len() as u64);
+                    for &(count, other) in edges {
+                        hasher.write_u64(count);
+                        hasher.write_u64(other as u64);
+                    }
+                }
+                hasher.0 as usize
+            })
+            .collect();
+        self.labels = labels;
+    }
+
+    fn sorted_labels(&self) -> Vec<usize> {
+        let mut labels = self.labels.clone();
+        labels.sort_unstable();
+        labels
+    }
+}
+
+// Compares the rulesets with color refinement (1-dimensional Weisfeiler-Lehman): every color
+// is repeatedly relabelled by its own label plus the labels and counts of its parents and
+// children, sharing labels between both rulesets, until the labelling stops getting finer.
+// Isomorphic rulesets always compare equal, but refinement can't tell apart some highly
+// regular non-isomorphic graphs, so true is only strong evidence of isomorphism.
+pub fn is_isomorphic(a: &[Bag], b: &[Bag]) -> bool {
+    let mut a = Refinement::new(a);
+    let mut b = Refinement::new(b);
+    if a.labels.len() != b.labels.len() {
+        return false;
+    }
+    let mut distinct = 1;
+    loop {
+        let mut interned = HashMap::new();
+        a.refine(&mut interned);
+        b.refine(&mut interned);
+        if a.sorted_labels() != b.sorted_labels() {
+            return false;
+        }
+        let refined = a.labels.iter().collect::<HashSet<_>>().len();
+        if refined == distinct {
+            return true;
+        }
+        distinct = refined;
+    }
+}
+
+// One (level, bags, distinct colors) row per level below color, level 1 being its direct
+// contents
+pub fn level_table(color: &str, graph: &Graph) -> Vec<(usize, u64, usize)> {
+    let mut table = Vec::new();
+    let mut frontier: HashMap<&str, u64> = HashMap::new();
+    frontier.insert(color, 1);
+    loop {
+        let mut next: HashMap<&str, u64> = HashMap::new();
+        for (color, copies) in frontier {
+            for content in graph.contents(color) {
+                *next.entry(&content.color).or_insert(0) += copies * content.count;
+            }
+        }
+        if next.is_empty() {
+            return table;
+        }
+        table.push((table.len() + 1, next.values().sum(), next.len()));
+        frontier = next;
+    }
+}
+
+fn _longest_chain<'a>(
+    color: &'a str,
+    graph: &'a Graph,
+    within: &HashSet<String>,
+    memo: &mut HashMap<&'a str, Vec<String>>,
+) -> Vec<String> {
+    if let Some(chain) = memo.get(color) {
+        return chain.clone();
+    }
+    let mut longest: Vec<String> = Vec::new();
+    for content in graph.contents(color) {
+        if within.contains(&content.color) {
+            let chain = _longest_chain(&content.color, graph, within, memo);
+            if chain.len() > longest.len() {
+                longest = chain;
+            }
+        }
+    }
+    longest.insert(0, color.to_string());
+    memo.insert(color, longest.clone());
+    longest
+}
+
+// The longest path through colors that both a and b contain. Ties go to the chain starting
+// with the alphabetically first color, then to the contents listed first in each rule.
+pub fn common_descendant_path(a: &str, b: &str, graph: &Graph) -> Vec<String> {
+    let b_descendants = graph.descendants(b);
+    let common: HashSet<String> = graph
+        .descendants(a)
+        .into_iter()
+        .filter(|color| b_descendants.contains(color))
+        .collect();
+    let mut starts: Vec<&String> = common.iter().collect();
+    starts.sort();
+    let mut memo = HashMap::new();
+    let mut longest = Vec::new();
+    for start in starts {
+        let chain = _longest_chain(start, graph, &common, &mut memo);
+        if chain.len() > longest.len() {
+            longest = chain;
+        }
+    }
+    longest
+}
+
+// (color strings held across all rules and contents, distinct colors they'd intern down to)
+pub fn interning_savings(bags: &[Bag]) -> (usize, usize) {
+    let instances = bags.iter().map(|bag| 1 + bag.contents.len()).sum();
+    let (colors, _) = index_colors(bags);
+    (instances, colors.len())
+}
+
+// The sort is stable, so repeated rules for a color keep their input order
+pub fn bags_sorted(mut bags: Vec<Bag>) -> Vec<Bag> {
+    bags.sort_by(|a, b| a.color.cmp(&b.color));
+    bags
+}
+
+// The roots themselves are only included if another root contains them
+pub fn reachable_from_any(roots: &[&str], graph: &Graph) -> HashSet<String> {
+    roots
+        .iter()
+        .flat_map(|root| graph.descendants(root))
+        .collect()
+}
+
+// Greedy set cover: keeps picking the root that reaches the most still-uncovered colors,
+// ties going to the alphabetically first, so the result is ordered by how much each root
+// adds. Greedy cover is only an approximation in general. Colors that no root reaches, like
+// those in a cycle nothing else holds, are left uncovered.
+pub fn min_roots_to_cover(bags: &[Bag]) -> Vec<String> {
+    let graph = Graph::new(bags);
+    let mut candidates: Vec<(String, HashSet<String>)> = root_bags(bags)
+        .into_iter()
+        .map(|root| {
+            let mut covers = graph.descendants(&root);
+            covers.insert(root.clone());
+            (root, covers)
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.0.cmp(&b.0));
+    let (colors, _) = index_colors(bags);
+    let mut uncovered: HashSet<String> = colors.into_iter().collect();
+    let mut chosen = Vec::new();
+    loop {
+        let best = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, (_, covers))| (i, covers.intersection(&uncovered).count()))
+            .filter(|&(_, gain)| gain > 0)
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+        match best {
+            Some((i, _)) => {
+                let (root, covers) = candidates.remove(i);
+                uncovered.retain(|color| !covers.contains(color));
+                chosen.push(root);
+            }
+            None => return chosen,
+        }
+    }
+}
+
+// Bags with a color in cutoffs are counted but never opened. color itself is always opened.
+pub fn find_bag_count_with_cutoffs(color: &str, cutoffs: &HashSet<String>, graph: &Graph) -> u64 {
+    graph
+        .contents(color)
+        .iter()
+        .map(|content| {
+            let inner = if cutoffs.contains(&content.color) {
+                0
+            } else {
+                find_bag_count_with_cutoffs(&content.color, cutoffs, graph)
+            };
+            content.count * (inner + 1)
+        })
+        .sum()
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn metrics_csv(bags: &[Bag]) -> String {
+    let graph = Graph::new(bags);
+    let contains = bags_to_contains_graph(bags);
+    let (colors, _) = index_colors(bags);
+    let mut csv = String::from(
+        "color,in_degree,out_degree,direct_count,total_count,max_depth,is_leaf,is_root\n",
+    );
+    for color in colors {
+        let contents = graph.contents(&color);
+        let in_degree = graph.contained_by.get(&color).map_or(0, HashSet::len);
+        let out_degree = contents
+            .iter()
+            .map(|content| &content.color)
+            .collect::<HashSet<_>>()
+            .len();
+        let direct_count: u64 = contents.iter().map(|content| content.count).sum();
+        let total_count = checked_bag_count(&color, &graph).expect("bag count overflowed");
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            csv_field(&color),
+            in_degree,
+            out_degree,
+            direct_count,
+            total_count,
+            max_depth(&color, &contains),
+            contents.is_empty(),
+            in_degree == 0
+        ));
+    }
+    csv
+}
+
+// An edge from parent to child closes a cycle if child already contains parent, or if they're
+// the same color
+pub fn would_cycle(parent: &str, child: &str, graph: &Graph) -> bool {
+    parent == child || graph.descendants(child).contains(parent)
+}
+
+// overrides maps (parent, child) edges to the count to use in place of the rule's own
+pub fn find_bag_count_with_overrides(
+    color: &str,
+    overrides: &HashMap<(String, String), u64>,
+    graph: &Graph,
+) -> u64 {
+    graph
+        .contents(color)
+        .iter()
+        .map(|content| {
+            let count = overrides
+                .get(&(color.to_string(), content.color.clone()))
+                .copied()
+                .unwrap_or(content.count);
+            count * (find_bag_count_with_overrides(&content.color, overrides, graph) + 1)
+        })
+        .sum()
+}
+
+// Empty rules no other rule refers to, sorted
+pub fn isolated_bags(bags: &[Bag]) -> Vec<String> {
+    let contained_by = bags_to_contained_by_graph(bags);
+    let mut isolated: Vec<String> = bags
+        .iter()
+        .filter(|bag| bag.contents.is_empty() && !contained_by.contains_key(&bag.color))
+        .map(|bag| bag.color.clone())
+        .collect();
+    isolated.sort();
+    isolated.dedup();
+    isolated
+}
+
+fn _render_tree(color: &str, graph: &Graph, depth: usize, lines: &mut Vec<String>) {
+    for content in graph.contents(color) {
+        lines.push(format!(
+            "{}{} {}",
+            "  ".repeat(depth),
+            content.count,
+            content.color
+        ));
+        _render_tree(&content.color, graph, depth + 1, lines);
+    }
+}
+
+fn tree_lines(color: &str, graph: &Graph) -> Vec<String> {
+    let mut lines = vec![color.to_string()];
+    _render_tree(color, graph, 1, &mut lines);
+    lines
+}
+
+// color on the first line, then each content indented under the bag holding it
+pub fn render_tree(color: &str, graph: &Graph) -> String {
+    tree_lines(color, graph)
+        .into_iter()
+        .map(|line| line + "\n")
+        .collect()
+}
+
+// The trees of color in old and new, diffed line by line. Each line starts with '-' if it's
+// only in the old tree, '+' if it's only in the new one and ' ' otherwise.
+pub fn tree_diff(old: &[Bag], new: &[Bag], color: &str) -> String {
+    let old = tree_lines(color, &Graph::new(old));
+    let new = tree_lines(color, &Graph::new(new));
+
+    // common[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push_str(&format!(" {}\n", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            diff.push_str(&format!("-{}\n", old[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+{}\n", new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+// Ranked by how many distinct bags list the color in their contents, ties broken by name
+pub fn top_content_colors(bags: &[Bag], k: usize) -> Vec<(String, usize)> {
+    let mut references: Vec<(String, usize)> = bags_to_contained_by_graph(bags)
+        .into_iter()
+        .map(|(color, contained_by)| (color, contained_by.len()))
+        .collect();
+    references.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    references.truncate(k);
+    references
+}
+
+// A problem found in the rule for color
+#[derive(Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub color: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} bags: {}", self.color, self.message)
+    }
+}
+
+// limits caps how many of a child color any single rule may hold. Children without a limit
+// are unconstrained.
+pub fn check_per_color_limits(bags: &[Bag], limits: &HashMap<String, u64>) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for bag in bags {
+        for content in bag.contents.iter() {
+            if let Some(&limit) = limits.get(&content.color) {
+                if content.count > limit {
+                    diagnostics.push(Diagnostic {
+                        color: bag.color.clone(),
+                        message: format!(
+                            "holds {} {} bags, more than the limit of {}",
+                            content.count, content.color, limit
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    diagnostics
+}
+
+// For every root, how many of the bags that can hold target it is or contains
+pub fn container_sources(target: &str, bags: &[Bag]) -> HashMap<String, usize> {
+    let graph = Graph::new(bags);
+    let containers = graph.ancestors(target);
+    root_bags(bags)
+        .into_iter()
+        .map(|root| {
+            let mut reachable = graph.descendants(&root);
+            reachable.insert(root.clone());
+            let count = reachable
+                .iter()
+                .filter(|color| containers.contains(*color))
+                .count();
+            (root, count)
+        })
+        .collect()
+}
+
+// Stops at the first bad line, returning its 1-based number and text with the error
+pub fn try_parse_rules(input: &str) -> result::Result<Vec<Bag>, (usize, String, ParseError)> {
+    let mut bags = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match Bag::try_from_rule(line) {
+            Ok(bag) => bags.push(bag),
+            Err(err) => return Err((i + 1, line.to_string(), err)),
+        }
+    }
+    Ok(bags)
+}
+
+// 64-bit FNV-1a, which unlike std's hashers is guaranteed to stay the same across builds
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write_u64(s.len() as u64);
+        self.write(s.as_bytes());
+    }
+}
+
+fn _subtree_hash<'a>(color: &'a str, graph: &'a Graph, memo: &mut HashMap<&'a str, u64>) -> u64 {
+    if let Some(&hash) = memo.get(color) {
+        return hash;
+    }
+    let mut children: Vec<&Content> = graph.contents(color).iter().collect();
+    children.sort_by(|a, b| a.color.cmp(&b.color).then(a.count.cmp(&b.count)));
+    let mut hasher = Fnv1a::new();
+    for content in children {
+        hasher.write_str(&content.color);
+        hasher.write_u64(content.count);
+        hasher.write_u64(_subtree_hash(&content.color, graph, memo));
+    }
+    memo.insert(color, hasher.0);
+    hasher.0
+}
+
+// A color's hash covers everything below it, the colors and counts of its contents and
+// theirs in turn, but not its own name. Bags holding the same things hash the same in any
+// ruleset, so the hash can key results cached from an earlier run.
+pub fn subtree_hashes(bags: &[Bag]) -> HashMap<String, u64> {
+    let graph = Graph::new(bags);
+    let mut memo = HashMap::new();
+    for bag in bags {
+        _subtree_hash(&bag.color, &graph, &mut memo);
+    }
+    memo.into_iter()
+        .map(|(color, hash)| (color.to_string(), hash))
+        .collect()
+}
+
+// The total number of bags you end up with after buying n target bags: the n bags themselves
+// plus everything they have to contain
+pub fn bags_to_own(target: &str, n: u64, graph: &Graph) -> u64 {
+    _checked_bag_count(target, graph)
+        .and_then(|count| count.checked_mul(n))
+        .expect("bag count overflowed")
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Summary {
+    pub colors: usize,
+    pub edges: usize,
+}
+
+pub fn summarize(bags: &[Bag]) -> Summary {
+    let (colors, _) = index_colors(bags);
+    Summary {
+        colors: colors.len(),
+        edges: aggregate_edges(bags).len(),
+    }
+}
+
+// Errors are reported on the same line, after the target
+pub fn one_line_summary(input: &str, target: &str) -> String {
+    match solve(input, target) {
+        Ok(solution) => {
+            let (bags, _) = parse_rules(input);
+            let summary = summarize(&bags);
+            format!(
+                "{}: {} containers, {} contained bags ({} colors, {} edges)",
+                target, solution.containers, solution.total_contents, summary.colors, summary.edges
+            )
+        }
+        Err(err) => format!("{}: {}", target, err),
+    }
+}
+
+#[derive(Debug)]
+pub enum BaselineMismatch {
+    Unreadable(io::Error),
+    Invalid(serde_json::Error),
+    Unsolvable(Error),
+    Field {
+        field: &'static str,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl fmt::Display for BaselineMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BaselineMismatch::Unreadable(err) => write!(f, "could not read baseline: {}", err),
+            BaselineMismatch::Invalid(err) => write!(f, "invalid baseline: {}", err),
+            BaselineMismatch::Unsolvable(err) => write!(f, "could not solve input: {}", err),
+            BaselineMismatch::Field {
+                field,
+                expected,
+                actual,
+            } => write!(f, "{} is {}, baseline has {}", field, actual, expected),
+        }
+    }
+}
+
+impl error::Error for BaselineMismatch {}
+
+// Solves input for target and compares it with a Solution saved as JSON, reporting the first
+// field that differs
+pub fn check_baseline(
+    input: &str,
+    target: &str,
+    baseline: &Path,
+) -> result::Result<(), BaselineMismatch> {
+    let saved = fs::read_to_string(baseline).map_err(BaselineMismatch::Unreadable)?;
+    let expected: Solution = serde_json::from_str(&saved).map_err(BaselineMismatch::Invalid)?;
+    let actual = solve(input, target).map_err(BaselineMismatch::Unsolvable)?;
+    let fields = [
+        (
+            "containers",
+            expected.containers as u64,
+            actual.containers as u64,
+        ),
+        (
+            "total_contents",
+            expected.total_contents,
+            actual.total_contents,
+        ),
+    ];
+    match fields
+        .iter()
+        .find(|(_, expected, actual)| expected != actual)
+    {
+        Some(&(field, expected, actual)) => Err(BaselineMismatch::Field {
+            field,
+            expected,
+            actual,
+        }),
+        None => Ok(()),
+    }
+}
+
+// When every bag below color holds a single color, the count is c1 + c1*c2 + c1*c2*c3 + ...
+// which is summed in one pass down the chain instead of recursing once per level, so very deep
+// chains can't overflow the stack. Anything that branches uses checked_bag_count instead.
+pub fn find_bag_count_fast(color: &str, graph: &Graph) -> u64 {
+    let mut total = 0u64;
+    let mut copies = 1u64;
+    let mut current = color;
+    for _ in 0..=graph.contains.len() {
+        match graph.contents(current) {
+            [] => return total,
+            [content] => {
+                copies = copies
+                    .checked_mul(content.count)
+                    .expect("bag count overflowed");
+                total = total.checked_add(copies).expect("bag count overflowed");
+                current = &content.color;
+            }
+            _ => break,
+        }
+    }
+    checked_bag_count(color, graph).expect("bag count overflowed")
+}
+
+pub fn describe(color: &str, graph: &Graph) -> String {
+    _describe(color, graph, &graph.contains_graph())
+}
+
+fn _describe(color: &str, graph: &Graph, contains: &HashMap<&str, HashSet<&Content>>) -> String {
+    let children = graph
+        .contents(color)
+        .iter()
+        .map(|content| &content.color)
+        .collect::<HashSet<_>>()
+        .len();
+    format!(
+        "{} → {} children, {} total, depth {}",
+        color,
+        children,
+        checked_bag_count(color, graph).expect("bag count overflowed"),
+        max_depth(color, contains)
+    )
+}
+
+// One describe line per color with a rule, sorted by color
+pub fn index_listing(graph: &Graph) -> Vec<String> {
+    let contains = graph.contains_graph();
+    let mut colors: Vec<&String> = graph.contains.keys().collect();
+    colors.sort();
+    colors
+        .into_iter()
+        .map(|color| _describe(color, graph, &contains))
+        .collect()
+}
+
+// (reachable from target only in a, reachable from target only in b)
+pub fn reachable_diff(a: &[Bag], b: &[Bag], target: &str) -> (HashSet<String>, HashSet<String>) {
+    let in_a = Graph::new(a).descendants(target);
+    let in_b = Graph::new(b).descendants(target);
+    let only_a = in_a.difference(&in_b).cloned().collect();
+    let only_b = in_b.difference(&in_a).cloned().collect();
+    (only_a, only_b)
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// Nodes are numbered in color order and carry the color as their label, edges point from a
+// bag to its contents with the count as their weight
+pub fn to_graphml(bags: &[Bag]) -> String {
+    let (colors, index) = index_colors(bags);
+    let mut edges: Vec<((String, String), u64)> = aggregate_edges(bags).into_iter().collect();
+    edges.sort();
+
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n",
+        "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"long\"/>\n",
+        "  <graph id=\"bags\" edgedefault=\"directed\">\n",
+    ));
+    for (i, color) in colors.iter().enumerate() {
+        xml.push_str(&format!(
+            "    <node id=\"n{}\"><data key=\"label\">{}</data></node>\n",
+            i,
+            xml_escape(color)
+        ));
+    }
+    for (i, ((parent, child), count)) in edges.iter().enumerate() {
+        xml.push_str(&format!(
+            "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\"><data key=\"weight\">{}</data></edge>\n",
+            i, index[parent], index[child], count
+        ));
+    }
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
+}
+
+// Like find_bag_count, but no rule may hold more of a color than its cap allows
+pub fn find_bag_count_capped(color: &str, caps: &HashMap<String, u64>, graph: &Graph) -> u64 {
+    graph
+        .contents(color)
+        .iter()
+        .map(|content| {
+            let count = caps
+                .get(&content.color)
+                .map_or(content.count, |&cap| content.count.min(cap));
+            count * (find_bag_count_capped(&content.color, caps, graph) + 1)
+        })
+        .sum()
+}
+
+// The empty bags somewhere inside color, not counting color itself
+pub fn subtree_leaves(color: &str, graph: &Graph) -> HashSet<String> {
+    graph
+        .descendants(color)
+        .into_iter()
+        .filter(|descendant| graph.contents(descendant).is_empty())
+        .collect()
+}
+
+fn _leaf_visit_counts(color: &str, times: u64, graph: &Graph, counts: &mut HashMap<String, u64>) {
+    for content in graph.contents(color) {
+        let reached = times * content.count;
+        if graph.contents(&content.color).is_empty() {
+            *counts.entry(content.color.clone()).or_insert(0) += reached;
+        } else {
+            _leaf_visit_counts(&content.color, reached, graph, counts);
+        }
+    }
+}
+
+// How many of each empty bag end up inside color. These add up to find_bag_count minus the
+// bags that hold something
+pub fn leaf_visit_counts(color: &str, graph: &Graph) -> HashMap<String, u64> {
+    let mut counts = HashMap::new();
+    _leaf_visit_counts(color, 1, graph, &mut counts);
+    counts
+}
+
+// find_bag_count reduced mod modulus at every step, so it never overflows. The arithmetic is
+// done in u128 so any u64 modulus is fine. Panics if modulus is 0
+pub fn find_bag_count_mod(color: &str, modulus: u64, graph: &Graph) -> u64 {
+    let wide_modulus = modulus as u128;
+    let total = graph.contents(color).iter().fold(0, |total, content| {
+        let inner = (find_bag_count_mod(&content.color, modulus, graph) as u128 + 1) % wide_modulus;
+        (total + content.count as u128 * inner % wide_modulus) % wide_modulus
+    });
+    total as u64
+}
+
+// The bag whose children hold the most even share of its contents, measured by the variance
+// of each child color's count times the bags it holds (itself included). Only bags holding at
+// least two different colors have anything to balance, and ties go to the first color
+// alphabetically
+pub fn most_balanced_subtree(bags: &[Bag]) -> Option<String> {
+    let graph = Graph::new(bags);
+    let mut variances: Vec<(f64, &str)> = bags
+        .iter()
+        .filter_map(|bag| {
+            let mut contributions: HashMap<&str, u64> = HashMap::new();
+            for content in bag.contents.iter() {
+                let contribution = _checked_bag_count(&content.color, &graph)
+                    .and_then(|count| count.checked_mul(content.count))
+                    .expect("bag count overflowed");
+                *contributions.entry(&content.color).or_insert(0) += contribution;
+            }
+            if contributions.len() < 2 {
+                return None;
+            }
+            let n = contributions.len() as f64;
+            let mean = contributions.values().map(|&c| c as f64).sum::<f64>() / n;
+            let variance = contributions
+                .values()
+                .map(|&contribution| (contribution as f64 - mean).powi(2))
+                .sum::<f64>()
+                / n;
+            Some((variance, bag.color.as_str()))
+        })
+        .collect();
+    variances.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap().then_with(|| a.1.cmp(b.1)));
+    variances.first().map(|(_, color)| color.to_string())
+}
+
+// Fewest containment steps from any root bag down to each color. Colors only reachable
+// through a cycle have no root above them and are left out
+pub fn depth_from_roots(bags: &[Bag]) -> HashMap<String, usize> {
+    let graph = Graph::new(bags);
+    let mut depths = HashMap::new();
+    let mut queue = VecDeque::new();
+    for root in root_bags(bags) {
+        depths.insert(root.clone(), 0);
+        queue.push_back(root);
+    }
+    while let Some(color) = queue.pop_front() {
+        let depth = depths[&color] + 1;
+        for content in graph.contents(&color) {
+            if !depths.contains_key(&content.color) {
+                depths.insert(content.color.clone(), depth);
+                queue.push_back(content.color.clone());
+            }
+        }
+    }
+    depths
+}
+
+// Just the rules needed to work anything out about target: its own and those of everything
+// it can hold, in input order
+pub fn minimal_ruleset_for(target: &str, bags: &[Bag]) -> Vec<Bag> {
+    let mut needed = Graph::new(bags).descendants(target);
+    needed.insert(target.to_string());
+    bags.iter()
+        .filter(|bag| needed.contains(&bag.color))
+        .cloned()
+        .collect()
+}
+
+pub fn subgraph_dot(target: &str, bags: &[Bag]) -> String {
+    to_dot(&minimal_ruleset_for(target, bags))
+}
+
+// How many colors have each max_depth, so leaves are counted under 0
+pub fn depth_histogram(bags: &[Bag]) -> BTreeMap<u64, usize> {
+    let graph = bags_to_contains_graph(bags);
+    let (colors, _) = index_colors(bags);
+    let mut histogram = BTreeMap::new();
+    for color in colors {
+        *histogram.entry(max_depth(&color, &graph)).or_insert(0) += 1;
+    }
+    histogram
+}
+
+// Up to n bags that fail pred, in input order, without checking the rest once n are found
+pub fn first_invalid<F: Fn(&Bag) -> bool>(bags: &[Bag], pred: F, n: usize) -> Vec<&Bag> {
+    bags.iter().filter(|bag| !pred(bag)).take(n).collect()
+}
+
+// A hash of the ruleset's shape and counts that ignores color names and rule order, from the
+// same refinement is_isomorphic uses. Isomorphic rulesets always share an id, and like
+// is_isomorphic a handful of very regular different ones might too
+pub fn structural_id(bags: &[Bag]) -> u64 {
+    let mut refinement = Refinement::new(bags);
+    let mut distinct = 1;
+    loop {
+        refinement.refine_hashed();
+        let refined = refinement.labels.iter().collect::<HashSet<_>>().len();
+        if refined == distinct {
+            break;
+        }
+        distinct = refined;
+    }
+    let mut hasher = Fnv1a::new();
+    for label in refinement.sorted_labels() {
+        hasher.write_u64(label as u64);
+    }
+    hasher.0
+}
+
+// Whether a leaf can be reached from target without going through the skipped edge
+fn _reaches_leaf_without(target: &str, skipped: Option<(&str, &str)>, graph: &Graph) -> bool {
+    let mut seen = HashSet::new();
+    let mut stack = vec![target];
+    while let Some(color) = stack.pop() {
+        if !seen.insert(color) {
+            continue;
+        }
+        let contents = graph.contents(color);
+        if contents.is_empty() {
+            return true;
+        }
+        for content in contents {
+            if Some((color, content.color.as_str())) != skipped {
+                stack.push(&content.color);
+            }
+        }
+    }
+    false
+}
+
+// Edges that lie on every path from target down to a leaf, sorted: the edges whose removal
+// leaves no leaf reachable from target. Each edge below target is tried in turn, so cycles
+// need no special handling. Repeated contents of the same color count as a single edge
+pub fn mandatory_edges(target: &str, graph: &Graph) -> Vec<(String, String)> {
+    if graph.contents(target).is_empty() || !_reaches_leaf_without(target, None, graph) {
+        return Vec::new();
+    }
+    let mut colors: Vec<String> = graph.descendants(target).into_iter().collect();
+    colors.push(target.to_string());
+    let mut edges: Vec<(&str, &str)> = colors
+        .iter()
+        .flat_map(|color| {
+            graph
+                .contents(color)
+                .iter()
+                .map(move |content| (color.as_str(), content.color.as_str()))
+        })
+        .collect();
+    edges.sort();
+    edges.dedup();
+    edges
+        .into_iter()
+        .filter(|&edge| !_reaches_leaf_without(target, Some(edge), graph))
+        .map(|(color, child)| (color.to_string(), child.to_string()))
+        .collect()
+}
+
+// Start at color and keep opening one bag from inside the current one until reaching a bag
+// with nothing in it, picking each kind of content with probability count / total count.
+// The expected number of steps is 0 for an empty bag and otherwise 1 plus the weighted mean
+// of the children's expectations. Assumes the rules have no cycles
+pub fn expected_walk_length(color: &str, graph: &Graph) -> f64 {
+    let contents = graph.contents(color);
+    let total: u64 = contents.iter().map(|content| content.count).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let weighted: f64 = contents
+        .iter()
+        .map(|content| content.count as f64 * expected_walk_length(&content.color, graph))
+        .sum();
+    1.0 + weighted / total as f64
+}
+
+// Parses input runs times and checks every run gives the same canonical text and the same
+// errors, to catch hash iteration order leaking into what parsing returns
+pub fn assert_deterministic(input: &str, runs: usize) -> bool {
+    let parse = || {
+        let (bags, errors) = parse_rules(input);
+        (canonical_text(&bags), errors)
+    };
+    let first = parse();
+    (1..runs).all(|_| parse() == first)
+}
+
+// The fewest edges to remove so nothing empty is left inside target, sorted. Every distinct
+// edge gets capacity 1 and every leaf drains into a shared sink, so by max-flow min-cut the
+// answer is the edges leaving what target can still reach once Edmonds-Karp saturates the
+// flow. Empty when target is a leaf itself, as no cut can separate it from itself
+pub fn min_cut_to_leaves(target: &str, graph: &Graph) -> Vec<(String, String)> {
+    if graph.contents(target).is_empty() {
+        return Vec::new();
+    }
+    let mut descendants = graph.descendants(target);
+    descendants.remove(target);
+    let mut nodes: Vec<String> = descendants.into_iter().collect();
+    nodes.sort();
+    nodes.insert(0, target.to_string());
+    let index: HashMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.as_str(), i))
+        .collect();
+    let sink = nodes.len();
+
+    let mut capacity: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut neighbours = vec![HashSet::new(); nodes.len() + 1];
+    let mut link = |from: usize, to: usize, amount: usize| {
+        capacity.insert((from, to), amount);
+        capacity.entry((to, from)).or_insert(0);
+        neighbours[from].insert(to);
+        neighbours[to].insert(from);
+    };
+    for (i, color) in nodes.iter().enumerate() {
+        let contents = graph.contents(color);
+        if contents.is_empty() {
+            link(i, sink, usize::MAX);
+        }
+        for content in contents {
+            link(i, index[content.color.as_str()], 1);
+        }
+    }
+
+    let reachable = |capacity: &HashMap<(usize, usize), usize>| {
+        let mut previous = vec![None; nodes.len() + 1];
+        previous[0] = Some(0);
+        let mut queue = VecDeque::new();
+        queue.push_back(0);
+        while let Some(node) = queue.pop_front() {
+            for &next in neighbours[node].iter() {
+                if previous[next].is_none() && capacity[&(node, next)] > 0 {
+                    previous[next] = Some(node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        previous
+    };
+    loop {
+        let previous = reachable(&capacity);
+        if previous[sink].is_none() {
+            break;
+        }
+        // Every augmenting path passes through at least one capacity 1 edge, so carries 1
+        let mut node = sink;
+        while node != 0 {
+            let from = previous[node].unwrap();
+            *capacity.get_mut(&(from, node)).unwrap() -= 1;
+            *capacity.get_mut(&(node, from)).unwrap() += 1;
+            node = from;
+        }
+    }
+
+    let source_side = reachable(&capacity);
+    let mut cut: Vec<(String, String)> = nodes
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| source_side[i].is_some())
+        .flat_map(|(_, color)| {
+            graph
+                .contents(color)
+                .iter()
+                .filter(|content| source_side[index[content.color.as_str()]].is_none())
+                .map(move |content| (color.clone(), content.color.clone()))
+        })
+        .collect();
+    cut.sort();
+    cut.dedup();
+    cut
+}
+
+// Each rule's direct content count as a fraction of total_weight, so the shares add up to 1.
+// Every share is 0 when no rule holds anything
+pub fn weight_shares(bags: &[Bag]) -> HashMap<String, f64> {
+    let total = total_weight(bags).max(1) as f64;
+    let mut shares = HashMap::new();
+    for bag in bags {
+        let weight: u64 = bag.contents.iter().map(|content| content.count).sum();
+        *shares.entry(bag.color.clone()).or_insert(0.0) += weight as f64 / total;
+    }
+    shares
+}
+
+// Colors below color that can be reached by opening at most budget bags. Reaching a color
+// means opening every bag on the way down: 1 dark olive inside a shiny gold, then the 3 faded
+// blue inside that, so 4 in all. Paths are explored fewest bags opened first, ties going to
+// the first color alphabetically. A color is only explored again by a path that leaves fewer
+// copies of it than every earlier one, since only then is anything further down cheaper
+pub fn reachable_within_budget(color: &str, budget: u64, graph: &Graph) -> HashSet<String> {
+    let mut explored: HashMap<&str, Vec<(u64, u64)>> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    queue.push(Reverse((0u64, color, 1u64)));
+    while let Some(Reverse((opened, current, copies))) = queue.pop() {
+        let paths = explored.entry(current).or_default();
+        if paths.iter().any(|&(o, c)| o <= opened && c <= copies) {
+            continue;
+        }
+        paths.push((opened, copies));
+        for content in graph.contents(current) {
+            let next = copies
+                .checked_mul(content.count)
+                .and_then(|next| opened.checked_add(next).map(|total| (total, next)));
+            if let Some((total, next)) = next {
+                if total <= budget {
+                    queue.push(Reverse((total, content.color.as_str(), next)));
+                }
+            }
+        }
+    }
+    explored.remove(color);
+    explored.into_keys().map(str::to_string).collect()
+}
+
+fn _to_folded<'a>(stack: &mut Vec<&'a str>, times: u64, graph: &'a Graph, folded: &mut String) {
+    let color = stack[stack.len() - 1];
+    for content in graph.contents(color) {
+        let copies = times * content.count;
+        stack.push(&content.color);
+        folded.push_str(&format!("{} {}\n", stack.join(";"), copies));
+        _to_folded(stack, copies, graph, folded);
+        stack.pop();
+    }
+}
+
+// One folded stack line per path below color, for inferno or flamegraph.pl. The number is
+// how many bags the last color on the path accounts for, so bags holding other bags get a
+// line of their own and the widths add up to find_bag_count
+pub fn to_folded(color: &str, graph: &Graph) -> String {
+    let mut folded = String::new();
+    _to_folded(&mut vec![color], 1, graph, &mut folded);
+    folded
+}
+
+// Up to k paths from from to to that never visit a color twice, shortest first. Paths of the
+// same length come out in the order of the rules' contents, as with shortest_path
+pub fn k_shortest_paths(from: &str, to: &str, k: usize, graph: &Graph) -> Vec<Vec<String>> {
+    let mut paths = Vec::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(vec![from]);
+    while let Some(path) = queue.pop_front() {
+        if paths.len() == k {
+            break;
+        }
+        let color = path[path.len() - 1];
+        if color == to {
+            paths.push(path.iter().map(|color| color.to_string()).collect());
+            continue;
+        }
+        for content in graph.contents(color) {
+            if !path.contains(&content.color.as_str()) {
+                let mut next = path.clone();
+                next.push(&content.color);
+                queue.push_back(next);
+            }
+        }
+    }
+    paths
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum CountMismatch {
+    Parse(usize, ParseError),
+    Count { expected: usize, actual: usize },
+}
+
+impl fmt::Display for CountMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CountMismatch::Parse(line, err) => write!(f, "line {}: {}", line, err),
+            CountMismatch::Count { expected, actual } => {
+                write!(f, "expected {} bags, parsed {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl error::Error for CountMismatch {}
+
+// Parses input and checks it holds exactly expected rules, failing on the first bad line
+pub fn expect_bag_count(input: &str, expected: usize) -> result::Result<Vec<Bag>, CountMismatch> {
+    let (bags, errors) = parse_rules(input);
+    if let Some((line, err)) = errors.into_iter().next() {
+        return Err(CountMismatch::Parse(line, err));
+    }
+    if bags.len() != expected {
+        return Err(CountMismatch::Count {
+            expected,
+            actual: bags.len(),
+        });
+    }
+    Ok(bags)
+}
+
+fn _adjective_chain<'a>(
+    color: &'a str,
+    graph: &'a Graph,
+    memo: &mut HashMap<&'a str, Vec<&'a str>>,
+) -> Vec<&'a str> {
+    if let Some(chain) = memo.get(color) {
+        return chain.clone();
+    }
+    let adjective = color.split(' ').next();
+    let mut longest = Vec::new();
+    for content in graph.contents(color) {
+        if content.color.split(' ').next() == adjective {
+            let chain = _adjective_chain(&content.color, graph, memo);
+            if chain.len() > longest.len() {
+                longest = chain;
+            }
+        }
+    }
+    longest.insert(0, color);
+    memo.insert(color, longest.clone());
+    longest
+}
+
+// The longest path starting at color or anywhere below it on which every color shares its
+// first word, e.g. dark red -> dark orange. Ties go to the path starting with the first
+// color alphabetically. Assumes the rules have no cycles
+pub fn longest_repeating_adjective_chain(color: &str, graph: &Graph) -> Vec<String> {
+    let mut starts: Vec<String> = graph.descendants(color).into_iter().collect();
+    starts.push(color.to_string());
+    starts.sort();
+    let mut memo = HashMap::new();
+    let mut longest = Vec::new();
+    for start in starts.iter() {
+        let chain = _adjective_chain(start, graph, &mut memo);
+        if chain.len() > longest.len() {
+            longest = chain;
+        }
+    }
+    longest.into_iter().map(str::to_string).collect()
+}
+
+// A hash of the ruleset's canonical text, so the same rules in any order share a fingerprint
+pub fn fingerprint(bags: &[Bag]) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write_str(&canonical_text(bags));
+    hasher.0
+}
+
+// 16 hex digits identifying the answers for target in input. Lines that fail to parse are
+// hashed in too, so input solve would reject never shares an ETag with input it accepts
+pub fn answer_etag(input: &str, target: &str) -> String {
+    let (bags, errors) = parse_rules(input);
+    let mut hasher = Fnv1a::new();
+    hasher.write_u64(fingerprint(&bags));
+    hasher.write_str(target);
+    hasher.write_u64(errors.len() as u64);
+    for (line, err) in errors {
+        hasher.write_u64(line as u64);
+        hasher.write_str(&err.to_string());
+    }
+    format!("{:016x}", hasher.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    const TEST_RULES: &str = indoc! {"\
+        light red bags contain 1 bright white bag, 2 muted yellow bags.
+        dark orange bags contain 3 bright white bags, 4 muted yellow bags.
+        bright white bags contain 1 shiny gold bag.
+        muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+        shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+        dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+        vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+        faded blue bags contain no other bags.
+        dotted black bags contain no other bags."};
+
+    const ALTERNATE_TEST_RULES: &str = indoc! {"\
+        shiny gold bags contain 2 dark red bags.
+        dark red bags contain 2 dark orange bags.
+        dark orange bags contain 2 dark yellow bags.
+        dark yellow bags contain 2 dark green bags.
+        dark green bags contain 2 dark blue bags.
+        dark blue bags contain 2 dark violet bags.
+        dark violet bags contain no other bags."};
+
+    #[test]
+    fn bag_has_correct_color_and_contents() {
+        let bag = Bag::new_from_rule(
+            "muted lime bags contain 1 wavy lime bag, 1 vibrant green bag, 3 light yellow bags.",
+        );
+        assert_eq!("muted lime", bag.color);
+        let wavy_lime = bag
+            .contents
+            .iter()
+            .find(|content| content.color == "wavy lime")
+            .expect("no wavy lime bag");
+        assert_eq!(1, wavy_lime.count);
+        let vibrant_green = bag
+            .contents
+            .iter()
+            .find(|content| content.color == "vibrant green")
+            .expect("no vibrant green");
+        assert_eq!(1, vibrant_green.count);
+        let light_yellow = bag
+            .contents
+            .iter()
+            .find(|content| content.color == "light yellow")
+            .expect("no light yellow");
+        assert_eq!(3, light_yellow.count);
+    }
+
+    fn to_line_results(data: &'static str) -> impl Iterator<Item = io::Result<String>> {
+        data.split('\n').map(|s| Ok(s.to_string()))
+    }
+
+    #[test]
+    fn bag_has_correct_color_but_no_contents() {
+        let bag = Bag::new_from_rule("dotted teal bags contain no other bags.");
+        assert_eq!("dotted teal", bag.color);
+        assert!(bag.contents.is_empty());
+    }
+
+    #[test]
+    fn converts_rules_to_bags() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        assert_eq!(9, bags.len());
+    }
+
+    #[test]
+    fn converts_bags_to_contained_by_graph() {
+        let graph = bags_to_contained_by_graph(&to_bags(to_line_results(TEST_RULES)));
+
+        assert_eq!(3, graph.get("faded blue").unwrap().len());
+    }
+
+    #[test]
+    fn finds_all_potential_containers() {
+        let graph = bags_to_contained_by_graph(&to_bags(to_line_results(TEST_RULES)));
+        let containers = find_potential_containers("shiny gold", &graph);
+        assert_eq!(4, containers.len());
+
+        let empty_containers = find_potential_containers("light red", &graph);
+        assert!(empty_containers.is_empty());
+    }
+
+    #[test]
+    fn converts_bags_to_contains_graph() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let graph = bags_to_contains_graph(&bags);
+        let light_red = graph.get("light red").unwrap();
+        assert_eq!(2, light_red.len());
+    }
+
+    #[test]
+    fn finds_correct_bag_count() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let graph = bags_to_contains_graph(&bags);
+        let count = find_bag_count("shiny gold", &graph);
+        assert_eq!(32, count);
+
+        let alternate_bags = to_bags(to_line_results(ALTERNATE_TEST_RULES));
+        let alternate_graph = bags_to_contains_graph(&alternate_bags);
+        let alternate_count = find_bag_count("shiny gold", &alternate_graph);
+        assert_eq!(126, alternate_count);
+    }
+
+    #[test]
+    fn indexes_colors_contiguously_in_sorted_order() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let (colors, index) = index_colors(&bags);
+        assert_eq!(9, colors.len());
+        assert_eq!(colors.len(), index.len());
+        assert!(colors.windows(2).all(|pair| pair[0] < pair[1]));
+        for (i, color) in colors.iter().enumerate() {
+            assert_eq!(i, index[color]);
+        }
+        assert_eq!("bright white", colors[0]);
+    }
+
+    #[test]
+    fn finds_ancestors_at_distance() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+
+        let parents = ancestors_at_distance("shiny gold", 1, &bags);
+        let expected: HashSet<String> = ["bright white", "muted yellow"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(expected, parents);
+
+        let grandparents = ancestors_at_distance("shiny gold", 2, &bags);
+        let expected: HashSet<String> = ["light red", "dark orange"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(expected, grandparents);
+    }
+
+    fn answers_output(part: Part) -> String {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let answers = solve_part("shiny gold", &bags, part);
+        let mut out = Vec::new();
+        write_answers(&answers, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn solves_each_part_independently() {
+        assert_eq!(
+            "The number of potential containers is 4\n",
+            answers_output(Part::One)
+        );
+        assert_eq!("You have to buy 32 bags\n", answers_output(Part::Two));
+        assert_eq!(
+            "The number of potential containers is 4\nYou have to buy 32 bags\n",
+            answers_output(Part::Both)
+        );
+    }
+
+    #[test]
+    fn parses_part() {
+        assert_eq!(Ok(Part::One), "1".parse::<Part>());
+        assert_eq!(Ok(Part::Two), "2".parse::<Part>());
+        assert!("3".parse::<Part>().is_err());
+    }
+
+    #[test]
+    fn finds_root_bags() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let expected: HashSet<String> = ["light red", "dark orange"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(expected, root_bags(&bags));
+    }
+
+    #[test]
+    fn finds_ruleset_height() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let graph = bags_to_contains_graph(&bags);
+        assert_eq!(2, max_depth("shiny gold", &graph));
+        assert_eq!(0, max_depth("faded blue", &graph));
+        assert_eq!(4, ruleset_height(&bags));
+
+        let alternate_bags = to_bags(to_line_results(ALTERNATE_TEST_RULES));
+        assert_eq!(6, ruleset_height(&alternate_bags));
+    }
+
+    #[test]
+    fn lists_all_reachable_pairs() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let pairs = all_reachable_pairs(&bags, usize::MAX).unwrap();
+        let has_pair = |ancestor: &str, descendant: &str| {
+            pairs.iter().any(|(a, d)| a == ancestor && d == descendant)
+        };
+        assert!(has_pair("light red", "shiny gold"));
+        assert!(has_pair("light red", "dotted black"));
+        assert!(has_pair("shiny gold", "faded blue"));
+        assert!(!has_pair("shiny gold", "light red"));
+        assert!(!has_pair("faded blue", "faded blue"));
+
+        assert_eq!(pairs, all_reachable_pairs(&bags, pairs.len()).unwrap());
+        assert_eq!(
+            TooMany {
+                limit: pairs.len() - 1
+            },
+            all_reachable_pairs(&bags, pairs.len() - 1).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn graph_knows_its_colors() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert!(graph.contains_color("shiny gold"));
+        assert!(!graph.contains_color("plaid magenta"));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert_eq!(
+            Some(ParseError::InvalidRule),
+            Bag::try_from_rule("this is not a rule").err()
+        );
+        assert_eq!(
+            Some(ParseError::InvalidContent("some red bags.".to_string())),
+            Bag::try_from_rule("dotted teal bags contain some red bags.").err()
+        );
+    }
+
+    #[test]
+    fn collects_parse_errors() {
+        let input = format!("{}\nnonsense\n\nmore nonsense", TEST_RULES);
+        let (bags, errors) = parse_rules(&input);
+        assert_eq!(9, bags.len());
+        assert_eq!(
+            vec![(10, ParseError::InvalidRule), (12, ParseError::InvalidRule)],
+            errors
+        );
+    }
+
+    #[test]
+    fn reports_parse_success_rate() {
+        let input = indoc! {"
+            light red bags contain 1 bright white bag, 2 muted yellow bags.
+            bright white bags contain 1 shiny gold bag.
+            this line is garbage
+            faded blue bags contain no other bags."};
+        let report = parse_report(input);
+        assert_eq!(4, report.total_lines);
+        assert_eq!(3, report.parsed);
+        assert_eq!(1, report.skipped);
+        assert!((report.success_rate - 0.75).abs() < f64::EPSILON);
+
+        let input = "a bags contain no other bags.\n\n   \nb bags contain no other bags.";
+        let report = parse_report(input);
+        assert_eq!(4, report.total_lines);
+        assert_eq!(2, report.parsed);
+        assert_eq!(2, report.skipped);
+        assert_eq!(1.0, report.success_rate);
+    }
+
+    #[test]
+    fn builds_spanning_tree() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let edge = |parent: &str, child: &str| (parent.to_string(), child.to_string());
+        assert_eq!(
+            vec![
+                edge("shiny gold", "dark olive"),
+                edge("shiny gold", "vibrant plum"),
+                edge("dark olive", "faded blue"),
+                edge("dark olive", "dotted black"),
+            ],
+            spanning_tree("shiny gold", &graph)
+        );
+
+        let tree = spanning_tree("light red", &graph);
+        assert_eq!(7, tree.len());
+        let children: HashSet<&String> = tree.iter().map(|(_, child)| child).collect();
+        assert_eq!(tree.len(), children.len());
+    }
+
+    #[test]
+    fn finds_scaled_bag_count() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(Some(32), checked_bag_count("shiny gold", &graph));
+        assert_eq!(96, find_bag_count_scaled("shiny gold", 3, &graph));
+        assert_eq!(0, find_bag_count_scaled("faded blue", 3, &graph));
+    }
+
+    #[test]
+    #[should_panic(expected = "bag count overflowed")]
+    fn scaled_bag_count_panics_on_overflow() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        find_bag_count_scaled("shiny gold", u64::MAX, &graph);
+    }
+
+    #[test]
+    fn canonicalizes_leaked_colors() {
+        let mut bags = vec![
+            Bag {
+                color: "shiny gold".to_string(),
+                contents: vec![Content {
+                    count: 1,
+                    color: "dark olive bag".to_string(),
+                }],
+                raw: None,
+            },
+            Bag {
+                color: "Shiny  Gold bag".to_string(),
+                contents: vec![Content {
+                    count: 2,
+                    color: "vibrant plum bags".to_string(),
+                }],
+                raw: None,
+            },
+            Bag {
+                color: "bright white".to_string(),
+                contents: vec![
+                    Content {
+                        count: 1,
+                        color: "shiny gold".to_string(),
+                    },
+                    Content {
+                        count: 2,
+                        color: "shiny gold bags".to_string(),
+                    },
+                ],
+                raw: None,
+            },
+        ];
+        canonicalize_colors(&mut bags);
+
+        assert_eq!(2, bags.len());
+        assert_eq!("shiny gold", bags[0].color);
+        let colors: Vec<&str> = bags[0]
+            .contents
+            .iter()
+            .map(|content| content.color.as_str())
+            .collect();
+        assert_eq!(vec!["dark olive", "vibrant plum"], colors);
+        assert_eq!(1, bags[1].contents.len());
+        assert_eq!("shiny gold", bags[1].contents[0].color);
+        assert_eq!(3, bags[1].contents[0].count);
+    }
+
+    #[test]
+    fn finds_degrees() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        assert_eq!((2, 2), degrees("shiny gold", &bags));
+        assert_eq!((0, 2), degrees("light red", &bags));
+        assert_eq!((3, 0), degrees("faded blue", &bags));
+        assert_eq!((0, 0), degrees("plaid magenta", &bags));
+    }
+
+    #[test]
+    fn finds_depth_limited_bag_count() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(0, find_bag_count_depth_limited("shiny gold", 0, &graph));
+        assert_eq!(3, find_bag_count_depth_limited("shiny gold", 1, &graph));
+        assert_eq!(32, find_bag_count_depth_limited("shiny gold", 2, &graph));
+        assert_eq!(
+            32,
+            find_bag_count_depth_limited("shiny gold", usize::MAX, &graph)
+        );
+    }
+
+    #[test]
+    fn finds_shortest_path() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(
+            Some(vec![
+                "light red".to_string(),
+                "bright white".to_string(),
+                "shiny gold".to_string(),
+                "dark olive".to_string(),
+            ]),
+            shortest_path("light red", "dark olive", &graph)
+        );
+        assert_eq!(
+            Some(vec!["shiny gold".to_string()]),
+            shortest_path("shiny gold", "shiny gold", &graph)
+        );
+        assert_eq!(None, shortest_path("shiny gold", "light red", &graph));
+    }
+
+    #[test]
+    fn runs_scripted_repl() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let script = indoc! {"
+            containers shiny gold
+            count shiny gold
+
+            path muted yellow to faded blue
+            path faded blue to shiny gold
+            count plaid magenta
+            containers plaid magenta
+            path muted yellow faded blue
+            frobnicate
+            quit
+            count shiny gold
+        "};
+        let mut out = Vec::new();
+        run_repl(&bags, io::Cursor::new(script), &mut out).unwrap();
+        assert_eq!(
+            indoc! {r#"
+                4
+                32
+                muted yellow -> faded blue
+                no path from faded blue to shiny gold
+                unknown color plaid magenta
+                unknown color plaid magenta
+                unknown command "path muted yellow faded blue"
+                unknown command "frobnicate"
+            "#},
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn runs_repl_on_colors_of_any_length() {
+        let rules = indoc! {"
+            foo bags contain 1 pale mauve green bag.
+            pale mauve green bags contain 2 bar bags.
+        "};
+        let bags = to_bags(to_line_results(rules.trim_end()));
+        let script = "path foo to bar\npath pale mauve green to bar\npath foo to\ncontainers bar\n";
+        let mut out = Vec::new();
+        run_repl(&bags, io::Cursor::new(script), &mut out).unwrap();
+        assert_eq!(
+            indoc! {r#"
+                foo -> pale mauve green -> bar
+                pale mauve green -> bar
+                unknown command "path foo to"
+                2
+            "#},
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn detects_forests() {
+        assert!(!is_forest(&to_bags(to_line_results(TEST_RULES))));
+        assert!(is_forest(&to_bags(to_line_results(ALTERNATE_TEST_RULES))));
+
+        let tree = indoc! {"
+            shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+            dark olive bags contain 3 faded blue bags.
+            vibrant plum bags contain 6 dotted black bags.
+            faded blue bags contain no other bags.
+            dotted black bags contain no other bags."};
+        assert!(is_forest(&to_bags(to_line_results(tree))));
+    }
+
+    #[test]
+    fn finds_graph_ancestors_and_descendants() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(4, graph.ancestors("shiny gold").len());
+        let expected: HashSet<String> =
+            ["dark olive", "vibrant plum", "faded blue", "dotted black"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+        assert_eq!(expected, graph.descendants("shiny gold"));
+    }
+
+    #[test]
+    fn finds_part1_part2_overlap() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert!(part1_part2_overlap("shiny gold", &graph).is_empty());
+
+        let cyclic = indoc! {"
+            shiny gold bags contain 1 dark olive bag.
+            dark olive bags contain 2 vibrant plum bags, 1 faded blue bag.
+            vibrant plum bags contain 1 shiny gold bag.
+            light red bags contain 1 shiny gold bag.
+            faded blue bags contain no other bags."};
+        let graph = Graph::new(&to_bags(to_line_results(cyclic)));
+        let expected: HashSet<String> = ["shiny gold", "dark olive", "vibrant plum"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(expected, part1_part2_overlap("shiny gold", &graph));
+    }
+
+    #[test]
+    fn writes_answers_as_env() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let mut out = Vec::new();
+        write_env(&solve_part("shiny gold", &bags, Part::Both), &mut out).unwrap();
+        assert_eq!(
+            "CONTAINERS=4\nBAG_COUNT=32\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn quotes_shell_values() {
+        assert_eq!("32", shell_quote("32"));
+        assert_eq!("''", shell_quote(""));
+        assert_eq!("'a b'", shell_quote("a b"));
+        assert_eq!(r"'it'\''s'", shell_quote("it's"));
+    }
+
+    #[test]
+    fn finds_child_balance() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(Some(3.0), child_balance("shiny gold", &graph));
+        assert_eq!(Some(1.0), child_balance("bright white", &graph));
+        assert_eq!(None, child_balance("faded blue", &graph));
+
+        let rules = indoc!(
+            "
+            a bags contain 0 b bags, 2 c bags, 1 d bag.
+            z bags contain 0 b bags.
+            b bags contain no other bags.
+            c bags contain no other bags.
+            d bags contain no other bags."
+        );
+        let graph = Graph::new(&to_bags(to_line_results(rules)));
+        assert_eq!(Some(2.0), child_balance("a", &graph));
+        assert_eq!(None, child_balance("z", &graph));
+    }
+
+    #[test]
+    fn displays_bags_as_rules() {
+        for rule in TEST_RULES.lines() {
+            assert_eq!(rule, Bag::new_from_rule(rule).to_string());
+        }
+    }
+
+    #[test]
+    fn canonicalizes_text_regardless_of_order() {
+        let reordered = indoc! {"
+            dotted black bags contain no other bags.
+            faded blue bags contain no other bags.
+            vibrant plum bags contain 6 dotted black bags, 5 faded blue bags.
+            dark olive bags contain 4 dotted black bags, 3 faded blue bags.
+            shiny gold bags contain 2 vibrant plum bags, 1 dark olive bag.
+            muted yellow bags contain 9 faded blue bags, 2 shiny gold bags.
+            bright white bags contain 1 shiny gold bag.
+            dark orange bags contain 4 muted yellow bags, 3 bright white bags.
+            light red bags contain 2 muted yellow bags, 1 bright white bag."};
+        let canonical = canonical_text(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(
+            canonical,
+            canonical_text(&to_bags(to_line_results(reordered)))
+        );
+        assert_eq!(
+            "bright white bags contain 1 shiny gold bag.",
+            canonical.lines().next().unwrap()
+        );
+        assert_eq!(
+            "dark olive bags contain 4 dotted black bags, 3 faded blue bags.",
+            canonical.lines().nth(1).unwrap()
+        );
+    }
+
+    #[test]
+    fn finds_popular_leaves() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        assert_eq!(
+            vec![
+                ("faded blue".to_string(), 3),
+                ("dotted black".to_string(), 2)
+            ],
+            popular_leaves(&bags, 2)
+        );
+        assert_eq!(
+            vec![("faded blue".to_string(), 3)],
+            popular_leaves(&bags, 3)
+        );
+    }
+
+    #[test]
+    fn strips_trailing_comments() {
+        let bag = Bag::new_from_rule(
+            "shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags. # the target",
+        );
+        assert_eq!("shiny gold", bag.color);
+        assert_eq!(2, bag.contents.len());
+        assert_eq!("vibrant plum", bag.contents[1].color);
+
+        let bag = Bag::new_from_rule("faded blue bags contain no other bags.# leaf");
+        assert!(bag.contents.is_empty());
+
+        assert!(Bag::try_from_rule("shiny gold bags contain 1 dark #olive bag.").is_err());
+    }
+
+    #[test]
+    fn finds_dominators() {
+        let rules = indoc! {"
+            shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+            dark olive bags contain 1 muted yellow bag.
+            vibrant plum bags contain 3 muted yellow bags.
+            muted yellow bags contain 4 faded blue bags, 1 dotted black bag.
+            faded blue bags contain 1 dotted black bag.
+            dotted black bags contain no other bags."};
+        let graph = Graph::new(&to_bags(to_line_results(rules)));
+        let idoms = dominators("shiny gold", &graph);
+        let idom = |color: &str| idoms.get(color).map(String::as_str);
+
+        assert_eq!(5, idoms.len());
+        assert_eq!(Some("shiny gold"), idom("dark olive"));
+        assert_eq!(Some("shiny gold"), idom("vibrant plum"));
+        assert_eq!(Some("shiny gold"), idom("muted yellow"));
+        assert_eq!(Some("muted yellow"), idom("faded blue"));
+        assert_eq!(Some("muted yellow"), idom("dotted black"));
+        assert_eq!(None, idom("shiny gold"));
+    }
+
+    #[test]
+    fn builds_content_multiset() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let multiset = content_multiset("shiny gold", &graph);
+        assert_eq!(4, multiset.len());
+        assert_eq!(13, multiset["faded blue"]);
+        assert_eq!(16, multiset["dotted black"]);
+        assert_eq!(32, multiset.values().sum::<u64>());
+    }
+
+    #[test]
+    fn counts_batch_of_queries() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let queries = [
+            ("shiny gold", "faded blue"),
+            ("light red", "shiny gold"),
+            ("shiny gold", "light red"),
+        ];
+        assert_eq!(vec![13, 5, 0], count_of_batch(&queries, &graph));
+    }
+
+    #[test]
+    fn converts_graph_back_into_bags() {
+        let mut graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        graph.contains.get_mut("shiny gold").unwrap()[1].count = 1;
+
+        let text: Vec<String> = graph.into_bags().iter().map(Bag::to_string).collect();
+        assert_eq!(9, text.len());
+        assert!(text.contains(
+            &"shiny gold bags contain 1 dark olive bag, 1 vibrant plum bag.".to_string()
+        ));
+
+        let bags: Vec<Bag> = text.iter().map(|rule| Bag::new_from_rule(rule)).collect();
+        let graph = bags_to_contains_graph(&bags);
+        assert_eq!(20, find_bag_count("shiny gold", &graph));
+    }
+
+    #[test]
+    fn sorts_contents_when_configured() {
+        let input = "dark orange bags contain 4 muted yellow bags, 3 bright white bags.";
+        let colors = |bags: &[Bag]| -> Vec<String> {
+            bags[0]
+                .contents
+                .iter()
+                .map(|content| content.color.clone())
+                .collect()
+        };
+
+        let (bags, _) = parse_rules(input);
+        assert_eq!(vec!["muted yellow", "bright white"], colors(&bags));
+
+        let config = Config {
+            sort_contents: true,
+            ..Config::default()
+        };
+        let (bags, _) = parse_rules_with_config(input, &config);
+        assert_eq!(vec!["bright white", "muted yellow"], colors(&bags));
+        assert_eq!(
+            "dark orange bags contain 3 bright white bags, 4 muted yellow bags.",
+            bags[0].to_string()
+        );
+    }
+
+    #[test]
+    fn counts_leaf_edges() {
+        assert_eq!(5, leaf_edge_count(&to_bags(to_line_results(TEST_RULES))));
+        assert_eq!(
+            1,
+            leaf_edge_count(&to_bags(to_line_results(ALTERNATE_TEST_RULES)))
+        );
+    }
+
+    #[test]
+    fn ranks_colors_by_container_count() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let ranked = colors_by_container_count(&bags);
+        assert_eq!(9, ranked.len());
+        assert_eq!(("dotted black".to_string(), 7), ranked[0]);
+        assert_eq!(("faded blue".to_string(), 7), ranked[1]);
+        assert_eq!(("shiny gold".to_string(), 4), ranked[4]);
+        assert_eq!(0, ranked[8].1);
+    }
+
+    #[test]
+    fn parses_within_budget() {
+        let bags = parse_within(TEST_RULES, Duration::from_secs(60)).unwrap();
+        assert_eq!(9, bags.len());
+    }
+
+    #[test]
+    fn times_out_parsing_large_input() {
+        let input: String = (0..100_000)
+            .map(|i| format!("shade{} gold bags contain 2 shade{} gold bags.\n", i, i + 1))
+            .collect();
+        match parse_within(&input, Duration::from_nanos(1)).unwrap_err() {
+            ParseWithinError::Timeout { parsed, elapsed } => {
+                // already past the budget at the first check
+                assert_eq!(0, parsed);
+                assert!(elapsed >= Duration::from_nanos(1));
+            }
+            err => panic!("expected a timeout, got {}", err),
+        }
+
+        // a clock that moves on a second per check runs out at the third, on line 128
+        let mut checks = 0;
+        let clock = || {
+            checks += 1;
+            Duration::from_secs(checks)
+        };
+        assert_eq!(
+            ParseWithinError::Timeout {
+                parsed: 2 * PARSE_DEADLINE_CHECK_INTERVAL,
+                elapsed: Duration::from_secs(3),
+            },
+            _parse_within(&input, Duration::from_secs(2), clock).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reports_bad_lines_parsing_within_budget() {
+        let input = "a bags contain no other bags.\n\nbad line\n";
+        assert_eq!(
+            ParseWithinError::Parse(3, ParseError::InvalidRule),
+            parse_within(input, Duration::from_secs(5)).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn finds_critical_colors() {
+        let rules = indoc! {"
+            shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+            dark olive bags contain 1 muted yellow bag.
+            vibrant plum bags contain 3 muted yellow bags.
+            muted yellow bags contain 4 faded blue bags, 1 dotted black bag.
+            faded blue bags contain no other bags.
+            dotted black bags contain no other bags."};
+        let graph = Graph::new(&to_bags(to_line_results(rules)));
+        assert_eq!(vec!["muted yellow"], critical_colors("shiny gold", &graph));
+
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert!(critical_colors("shiny gold", &graph).is_empty());
+        assert_eq!(vec!["shiny gold"], critical_colors("bright white", &graph));
+    }
+
+    #[test]
+    fn finds_total_weight() {
+        assert_eq!(43, total_weight(&to_bags(to_line_results(TEST_RULES))));
+        assert_eq!(
+            12,
+            total_weight(&to_bags(to_line_results(ALTERNATE_TEST_RULES)))
+        );
+    }
+
+    #[test]
+    fn round_trips_graph_through_bytes() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let bytes = to_bytes(&Graph::new(&bags));
+        let graph = from_bytes(&bytes).unwrap();
+        assert_eq!(Some(32), checked_bag_count("shiny gold", &graph));
+        assert_eq!(canonical_text(&bags), canonical_text(&graph.into_bags()));
+    }
+
+    #[test]
+    fn round_trips_large_varints() {
+        for &value in &[0, 1, 127, 128, 300, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value);
+            let mut decoder = Decoder { bytes: &bytes };
+            assert_eq!(Ok(value), decoder.varint());
+            assert!(decoder.bytes.is_empty());
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_bytes() {
+        let bytes = to_bytes(&Graph::new(&to_bags(to_line_results(TEST_RULES))));
+        assert_eq!(
+            Some(DecodeError::UnexpectedEof),
+            from_bytes(&bytes[..bytes.len() - 1]).err()
+        );
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            Some(DecodeError::TrailingBytes),
+            from_bytes(&trailing).err()
+        );
+        assert_eq!(
+            Some(DecodeError::VarintTooLong),
+            from_bytes(&[0xff; 11]).err()
+        );
+    }
+
+    #[test]
+    fn ranks_descendant_counts() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let ranked = descendant_counts_ranked("shiny gold", &graph);
+        assert_eq!(
+            vec![
+                ("dotted black".to_string(), 16),
+                ("faded blue".to_string(), 13),
+                ("vibrant plum".to_string(), 2),
+                ("dark olive".to_string(), 1),
+            ],
+            ranked
+        );
+    }
+
+    #[test]
+    fn checks_ruleset_is_self_contained() {
+        let mut bags = to_bags(to_line_results(TEST_RULES));
+        assert!(is_self_contained(&bags));
+
+        bags.retain(|bag| bag.color != "faded blue");
+        assert!(!is_self_contained(&bags));
+    }
+
+    #[test]
+    fn counts_distinct_colors_within_depth() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(0, distinct_colors_within_depth("shiny gold", 0, &graph));
+        assert_eq!(2, distinct_colors_within_depth("shiny gold", 1, &graph));
+        assert_eq!(4, distinct_colors_within_depth("shiny gold", 2, &graph));
+        assert_eq!(4, distinct_colors_within_depth("shiny gold", 10, &graph));
+    }
+
+    #[test]
+    fn renders_reverse_dot() {
+        let dot = to_dot_reverse(&to_bags(to_line_results(TEST_RULES)));
+        assert!(dot.starts_with("digraph contained_by {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("    \"faded blue\" -> \"muted yellow\";\n"));
+        assert!(!dot.contains("\"muted yellow\" -> \"faded blue\""));
+        assert_eq!(13, dot.matches(" -> ").count());
+    }
+
+    #[test]
+    fn escapes_dot_ids() {
+        assert_eq!(r#""shiny gold""#, dot_id("shiny gold"));
+        assert_eq!(r#""a \"b\" \\c""#, dot_id(r#"a "b" \c"#));
+    }
+
+    #[test]
+    fn counts_ancestors_affected_by_edge() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(5, ancestors_affected("dark olive", "shiny gold", &graph));
+        assert_eq!(1, ancestors_affected("bright white", "light red", &graph));
+        assert_eq!(0, ancestors_affected("light red", "shiny gold", &graph));
+    }
+
+    #[test]
+    fn solves_input_in_one_call() {
+        assert_eq!(
+            Ok(Solution {
+                containers: 4,
+                total_contents: 32
+            }),
+            solve(TEST_RULES, "shiny gold")
+        );
+        assert_eq!(
+            Err(Error::UnknownColor("plaid magenta".to_string())),
+            solve(TEST_RULES, "plaid magenta")
+        );
+        assert_eq!(
+            Err(Error::Parse(2, ParseError::InvalidRule)),
+            solve(
+                "faded blue bags contain no other bags.\nnonsense",
+                "faded blue"
+            )
+        );
+    }
+
+    #[test]
+    fn finds_top_containers() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let expected: HashSet<String> = ["light red", "dark orange"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(expected, top_containers("shiny gold", &bags));
+        assert!(top_containers("light red", &bags).is_empty());
+    }
+
+    #[test]
+    fn flattens_contents_with_paths() {
+        let graph = Graph::new(&to_bags(to_line_results(ALTERNATE_TEST_RULES)));
+        let paths: Vec<(Vec<String>, u64)> = flatten_with_paths("shiny gold", &graph).collect();
+        assert_eq!(1, paths.len());
+        let (path, product) = &paths[0];
+        assert_eq!(7, path.len());
+        assert_eq!("shiny gold", path[0]);
+        assert_eq!("dark violet", path[6]);
+        assert_eq!(64, *product);
+        assert_eq!(126, checked_bag_count("shiny gold", &graph).unwrap());
+
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let paths: Vec<(Vec<String>, u64)> = flatten_with_paths("shiny gold", &graph).collect();
+        assert_eq!(4, paths.len());
+        assert_eq!(vec!["shiny gold", "dark olive", "faded blue"], paths[0].0);
+        assert_eq!(3, paths[0].1);
+        assert_eq!(29, paths.iter().map(|(_, product)| product).sum::<u64>());
+        assert_eq!(0, flatten_with_paths("faded blue", &graph).count());
+    }
+
+    #[test]
+    fn counts_unique_subtrees() {
+        assert_eq!(
+            8,
+            unique_subtree_count(&to_bags(to_line_results(TEST_RULES)))
+        );
+
+        let shared = indoc! {"
+            shiny gold bags contain 1 dark olive bag, 1 vibrant plum bag.
+            dark olive bags contain 3 faded blue bags.
+            vibrant plum bags contain 3 dotted black bags.
+            faded blue bags contain no other bags.
+            dotted black bags contain no other bags."};
+        assert_eq!(3, unique_subtree_count(&to_bags(to_line_results(shared))));
+    }
+
+    #[test]
+    fn solves_each_target_with_callback() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let mut results = Vec::new();
+        solve_each(
+            &["shiny gold", "dark olive"],
+            &bags,
+            |target, containers, count| results.push((target.to_string(), containers, count)),
+        );
+        assert_eq!(
+            vec![
+                ("shiny gold".to_string(), 4, 32),
+                ("dark olive".to_string(), 5, 7)
+            ],
+            results
+        );
+    }
+
+    #[test]
+    fn finds_min_colors_to_leaf() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(3, min_colors_to_leaf("shiny gold", &graph));
+        assert_eq!(2, min_colors_to_leaf("muted yellow", &graph));
+        assert_eq!(1, min_colors_to_leaf("faded blue", &graph));
+
+        let cyclic = indoc! {"
+            shiny gold bags contain 1 dark olive bag.
+            dark olive bags contain 1 shiny gold bag."};
+        let graph = Graph::new(&to_bags(to_line_results(cyclic)));
+        assert_eq!(0, min_colors_to_leaf("shiny gold", &graph));
+    }
+
+    #[test]
+    fn classifies_bags() {
+        let mut bags = to_bags(to_line_results(TEST_RULES));
+        bags.push(Bag::new_from_rule(
+            "plaid magenta bags contain no other bags.",
+        ));
+        let (roots, internal, leaves) = classify_bags(&bags);
+        assert_eq!(vec!["dark orange", "light red", "plaid magenta"], roots);
+        assert_eq!(
+            vec![
+                "bright white",
+                "dark olive",
+                "muted yellow",
+                "shiny gold",
+                "vibrant plum"
+            ],
+            internal
+        );
+        assert_eq!(vec!["dotted black", "faded blue"], leaves);
+    }
+
+    #[test]
+    fn aggregates_edges() {
+        let mut bags = to_bags(to_line_results(TEST_RULES));
+        let edges = aggregate_edges(&bags);
+        let edge = |parent: &str, child: &str| (parent.to_string(), child.to_string());
+        assert_eq!(13, edges.len());
+        assert_eq!(9, edges[&edge("muted yellow", "faded blue")]);
+        assert_eq!(2, edges[&edge("shiny gold", "vibrant plum")]);
+
+        bags.push(Bag::new_from_rule(
+            "shiny gold bags contain 3 vibrant plum bags.",
+        ));
+        let edges = aggregate_edges(&bags);
+        assert_eq!(13, edges.len());
+        assert_eq!(5, edges[&edge("shiny gold", "vibrant plum")]);
+    }
+
+    #[test]
+    fn preserves_raw_rule_text_when_configured() {
+        let input = "faded blue bags contain no other bags. # a leaf";
+        let (bags, _) = parse_rules(input);
+        assert_eq!(None, bags[0].raw);
+
+        let config = Config {
+            preserve_raw: true,
+            ..Config::default()
+        };
+        let (bags, _) = parse_rules_with_config(input, &config);
+        assert_eq!(Some(input), bags[0].raw.as_deref());
+    }
+
+    #[test]
+    fn finds_diameter() {
+        assert_eq!(6, diameter(&to_bags(to_line_results(ALTERNATE_TEST_RULES))));
+        assert_eq!(4, diameter(&to_bags(to_line_results(TEST_RULES))));
+    }
+
+    #[test]
+    fn finds_superset_pairs() {
+        assert_eq!(
+            vec![
+                ("dark orange".to_string(), "light red".to_string()),
+                ("muted yellow".to_string(), "bright white".to_string()),
+                ("vibrant plum".to_string(), "dark olive".to_string()),
+            ],
+            superset_pairs(&to_bags(to_line_results(TEST_RULES)))
+        );
+
+        let rules = indoc! {"
+            shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+            dark orange bags contain 1 dark olive bag, 3 vibrant plum bags, 1 faded blue bag.
+            light red bags contain 1 dark olive bag, 2 vibrant plum bags.
+            dark olive bags contain no other bags.
+            vibrant plum bags contain no other bags.
+            faded blue bags contain no other bags."};
+        assert_eq!(
+            vec![
+                ("dark orange".to_string(), "light red".to_string()),
+                ("dark orange".to_string(), "shiny gold".to_string()),
+            ],
+            superset_pairs(&to_bags(to_line_results(rules)))
+        );
+    }
+
+    #[test]
+    fn scores_centrality() {
+        let scores = centrality(&to_bags(to_line_results(TEST_RULES)));
+        let (top, score) = scores
+            .iter()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .unwrap();
+        assert_eq!("shiny gold", top);
+        assert!((score - 0.25).abs() < 1e-9);
+        assert_eq!(0.0, scores["light red"]);
+        assert_eq!(0.0, scores["faded blue"]);
+    }
+
+    fn rename_colors(rules: &str) -> String {
+        let names = [
+            ("light red", "color a"),
+            ("dark orange", "color b"),
+            ("bright white", "color c"),
+            ("muted yellow", "color d"),
+            ("shiny gold", "color e"),
+            ("dark olive", "color f"),
+            ("vibrant plum", "color g"),
+            ("faded blue", "color h"),
+            ("dotted black", "color i"),
+        ];
+        let mut lines: Vec<String> = rules
+            .lines()
+            .map(|line| {
+                names
+                    .iter()
+                    .fold(line.to_string(), |line, (from, to)| line.replace(from, to))
+            })
+            .collect();
+        lines.reverse();
+        lines.join("\n")
+    }
+
+    #[test]
+    fn detects_isomorphic_rulesets() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let renamed = rename_colors(TEST_RULES);
+        let renamed_bags: Vec<Bag> = renamed.lines().map(Bag::new_from_rule).collect();
+        assert!(is_isomorphic(&bags, &renamed_bags));
+        assert!(is_isomorphic(&bags, &bags));
+
+        let mut changed = renamed_bags.clone();
+        changed[0].contents.push(Content {
+            count: 1,
+            color: "color h".to_string(),
+        });
+        assert!(!is_isomorphic(&bags, &changed));
+
+        let alternate = to_bags(to_line_results(ALTERNATE_TEST_RULES));
+        assert!(!is_isomorphic(&bags, &alternate));
+    }
+
+    #[test]
+    fn builds_level_table() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(
+            vec![(1, 3, 2), (2, 29, 2)],
+            level_table("shiny gold", &graph)
+        );
+        let table = level_table("light red", &graph);
+        assert_eq!((1, 3, 2), table[0]);
+        assert_eq!((2, 23, 2), table[1]);
+        assert!(level_table("faded blue", &graph).is_empty());
+    }
+
+    #[test]
+    fn finds_common_descendant_path() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(
+            vec!["dark olive", "faded blue"],
+            common_descendant_path("muted yellow", "shiny gold", &graph)
+        );
+        assert_eq!(
+            vec!["bright white", "shiny gold", "dark olive", "faded blue"],
+            common_descendant_path("light red", "dark orange", &graph)
+        );
+        assert!(common_descendant_path("faded blue", "shiny gold", &graph).is_empty());
+    }
+
+    #[test]
+    fn measures_interning_savings() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        assert_eq!((22, 9), interning_savings(&bags));
+    }
+
+    #[test]
+    fn sorts_bags_by_color() {
+        let bags = bags_sorted(to_bags(to_line_results(TEST_RULES)));
+        let colors: Vec<&str> = bags.iter().map(|bag| bag.color.as_str()).collect();
+        assert_eq!(
+            vec![
+                "bright white",
+                "dark olive",
+                "dark orange",
+                "dotted black",
+                "faded blue",
+                "light red",
+                "muted yellow",
+                "shiny gold",
+                "vibrant plum"
+            ],
+            colors
+        );
+    }
+
+    #[test]
+    fn finds_colors_reachable_from_any_root() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let expected: HashSet<String> = [
+            "shiny gold",
+            "dark olive",
+            "vibrant plum",
+            "faded blue",
+            "dotted black",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(
+            expected,
+            reachable_from_any(&["bright white", "dark olive"], &graph)
+        );
+        assert!(reachable_from_any(&[], &graph).is_empty());
+    }
+
+    #[test]
+    fn finds_roots_covering_every_color() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let roots = min_roots_to_cover(&bags);
+        assert_eq!(vec!["dark orange", "light red"], roots);
+
+        let graph = Graph::new(&bags);
+        let mut covered = reachable_from_any(
+            &roots.iter().map(String::as_str).collect::<Vec<_>>(),
+            &graph,
+        );
+        covered.extend(roots.iter().cloned());
+        let (colors, _) = index_colors(&bags);
+        assert_eq!(colors.into_iter().collect::<HashSet<_>>(), covered);
+    }
+
+    #[test]
+    fn finds_bag_count_with_cutoffs() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let mut cutoffs = HashSet::new();
+        assert_eq!(
+            32,
+            find_bag_count_with_cutoffs("shiny gold", &cutoffs, &graph)
+        );
+
+        cutoffs.insert("dark olive".to_string());
+        assert_eq!(
+            25,
+            find_bag_count_with_cutoffs("shiny gold", &cutoffs, &graph)
+        );
+        assert_eq!(
+            7,
+            find_bag_count_with_cutoffs("dark olive", &cutoffs, &graph)
+        );
+    }
+
+    #[test]
+    fn writes_metrics_csv() {
+        let csv = metrics_csv(&to_bags(to_line_results(TEST_RULES)));
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(10, lines.len());
+        assert_eq!(
+            "color,in_degree,out_degree,direct_count,total_count,max_depth,is_leaf,is_root",
+            lines[0]
+        );
+        assert_eq!("bright white,2,1,1,33,3,false,false", lines[1]);
+        assert!(lines.contains(&"shiny gold,2,2,3,32,2,false,false"));
+        assert!(lines.contains(&"faded blue,3,0,0,0,0,true,false"));
+        assert!(lines.contains(&"light red,0,2,3,186,4,false,true"));
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!("shiny gold", csv_field("shiny gold"));
+        assert_eq!(r#""a, ""b""""#, csv_field(r#"a, "b""#));
+    }
+
+    #[test]
+    fn detects_edges_that_would_cycle() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert!(would_cycle("faded blue", "light red", &graph));
+        assert!(would_cycle("dark olive", "shiny gold", &graph));
+        assert!(would_cycle("shiny gold", "shiny gold", &graph));
+        assert!(!would_cycle("light red", "faded blue", &graph));
+        assert!(!would_cycle("faded blue", "dotted black", &graph));
+    }
+
+    #[test]
+    fn finds_bag_count_with_overrides() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let mut overrides = HashMap::new();
+        assert_eq!(
+            32,
+            find_bag_count_with_overrides("shiny gold", &overrides, &graph)
+        );
+
+        overrides.insert(("shiny gold".to_string(), "vibrant plum".to_string()), 1);
+        assert_eq!(
+            20,
+            find_bag_count_with_overrides("shiny gold", &overrides, &graph)
+        );
+        overrides.insert(("dark olive".to_string(), "faded blue".to_string()), 0);
+        assert_eq!(
+            17,
+            find_bag_count_with_overrides("shiny gold", &overrides, &graph)
+        );
+        assert_eq!(Some(32), checked_bag_count("shiny gold", &graph));
+    }
+
+    #[test]
+    fn finds_isolated_bags() {
+        let mut bags = to_bags(to_line_results(TEST_RULES));
+        assert!(isolated_bags(&bags).is_empty());
+
+        bags.push(Bag::new_from_rule("foo bags contain no other bags."));
+        assert_eq!(vec!["foo"], isolated_bags(&bags));
+    }
+
+    #[test]
+    fn renders_tree() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(
+            indoc! {"
+                shiny gold
+                  1 dark olive
+                    3 faded blue
+                    4 dotted black
+                  2 vibrant plum
+                    5 faded blue
+                    6 dotted black
+            "},
+            render_tree("shiny gold", &graph)
+        );
+    }
+
+    #[test]
+    fn diffs_trees() {
+        let old = to_bags(to_line_results(TEST_RULES));
+        let mut new = old.clone();
+        let dark_olive = new
+            .iter_mut()
+            .find(|bag| bag.color == "dark olive")
+            .unwrap();
+        dark_olive.contents[0].count = 5;
+
+        let diff = tree_diff(&old, &new, "shiny gold");
+        let changed: Vec<&str> = diff.lines().filter(|line| !line.starts_with(' ')).collect();
+        assert_eq!(vec!["-    3 faded blue", "+    5 faded blue"], changed);
+        assert_eq!(8, diff.lines().count());
+        assert!(diff.starts_with(" shiny gold\n   1 dark olive\n"));
+
+        let unchanged = tree_diff(&old, &old, "shiny gold");
+        assert!(unchanged.lines().all(|line| line.starts_with(' ')));
+    }
+
+    #[test]
+    fn finds_top_content_colors() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        assert_eq!(
+            vec![
+                ("faded blue".to_string(), 3),
+                ("bright white".to_string(), 2)
+            ],
+            top_content_colors(&bags, 2)
+        );
+        assert_eq!(7, top_content_colors(&bags, 100).len());
+    }
+
+    #[test]
+    fn checks_per_color_limits() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let mut limits = HashMap::new();
+        limits.insert("faded blue".to_string(), 5);
+        limits.insert("shiny gold".to_string(), 2);
+        let diagnostics = check_per_color_limits(&bags, &limits);
+        assert_eq!(
+            vec![Diagnostic {
+                color: "muted yellow".to_string(),
+                message: "holds 9 faded blue bags, more than the limit of 5".to_string(),
+            }],
+            diagnostics
+        );
+        assert_eq!(
+            "muted yellow bags: holds 9 faded blue bags, more than the limit of 5",
+            diagnostics[0].to_string()
+        );
+    }
+
+    #[test]
+    fn attributes_containers_to_roots() {
+        let mut bags = to_bags(to_line_results(TEST_RULES));
+        bags.push(Bag::new_from_rule(
+            "plaid magenta bags contain 1 faded blue bag.",
+        ));
+        let sources = container_sources("shiny gold", &bags);
+        assert_eq!(3, sources.len());
+        assert_eq!(3, sources["light red"]);
+        assert_eq!(3, sources["dark orange"]);
+        assert_eq!(0, sources["plaid magenta"]);
+    }
+
+    #[test]
+    fn reports_first_bad_line_with_context() {
+        assert_eq!(9, try_parse_rules(TEST_RULES).unwrap().len());
+
+        let input = indoc! {"
+            shiny gold bags contain 1 dark olive bag.
+
+            dark olive bags contain lots of faded blue bags.
+            nonsense"};
+        let (line, text, err) = try_parse_rules(input).unwrap_err();
+        assert_eq!(3, line);
+        assert_eq!("dark olive bags contain lots of faded blue bags.", text);
+        assert_eq!(
+            ParseError::InvalidContent("lots of faded blue bags.".to_string()),
+            err
+        );
+    }
+
+    #[test]
+    fn hashes_subtrees_by_content() {
+        let hashes = subtree_hashes(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(9, hashes.len());
+        assert_eq!(hashes["faded blue"], hashes["dotted black"]);
+        assert_ne!(hashes["dark olive"], hashes["vibrant plum"]);
+
+        let other = indoc! {"
+            plaid magenta bags contain 2 vibrant plum bags, 1 dark olive bag.
+            dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+            vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+            faded blue bags contain no other bags.
+            dotted black bags contain no other bags."};
+        let other_hashes = subtree_hashes(&to_bags(to_line_results(other)));
+        assert_eq!(hashes["shiny gold"], other_hashes["plaid magenta"]);
+        assert_eq!(hashes["dark olive"], other_hashes["dark olive"]);
+    }
+
+    #[test]
+    fn hashes_with_stable_fnv1a() {
+        let mut hasher = Fnv1a::new();
+        hasher.write(b"a");
+        assert_eq!(0xaf63_dc4c_8601_ec8c, hasher.0);
+    }
+
+    #[test]
+    fn counts_bags_to_own() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(33, bags_to_own("shiny gold", 1, &graph));
+        assert_eq!(99, bags_to_own("shiny gold", 3, &graph));
+        assert_eq!(4, bags_to_own("faded blue", 4, &graph));
+        assert_eq!(0, bags_to_own("shiny gold", 0, &graph));
+    }
+
+    #[test]
+    fn summarizes_ruleset() {
+        assert_eq!(
+            Summary {
+                colors: 9,
+                edges: 13
+            },
+            summarize(&to_bags(to_line_results(TEST_RULES)))
+        );
+    }
+
+    #[test]
+    fn writes_one_line_summary() {
+        assert_eq!(
+            "shiny gold: 4 containers, 32 contained bags (9 colors, 13 edges)",
+            one_line_summary(TEST_RULES, "shiny gold")
+        );
+        assert_eq!(
+            "plaid magenta: no rule for plaid magenta bags",
+            one_line_summary(TEST_RULES, "plaid magenta")
+        );
+    }
+
+    fn write_baseline(name: &str, contents: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("adv2020_7-{}-{}.json", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn checks_solution_against_baseline() {
+        let saved = serde_json::to_string(&solve(TEST_RULES, "shiny gold").unwrap()).unwrap();
+        let matching = write_baseline("matching", &saved);
+        assert!(check_baseline(TEST_RULES, "shiny gold", &matching).is_ok());
+        assert!(matches!(
+            check_baseline(TEST_RULES, "dark olive", &matching),
+            Err(BaselineMismatch::Field { .. })
+        ));
+        let saved = serde_json::to_string(&solve(TEST_RULES, "dark olive").unwrap()).unwrap();
+        let other_target = write_baseline("other-target", &saved);
+        assert!(check_baseline(TEST_RULES, "dark olive", &other_target).is_ok());
+
+        let mismatching =
+            write_baseline("mismatching", r#"{"containers": 4, "total_contents": 33}"#);
+        match check_baseline(TEST_RULES, "shiny gold", &mismatching) {
+            Err(BaselineMismatch::Field {
+                field,
+                expected,
+                actual,
+            }) => {
+                assert_eq!("total_contents", field);
+                assert_eq!(33, expected);
+                assert_eq!(32, actual);
+            }
+            other => panic!("expected a field mismatch, got {:?}", other),
+        }
+
+        let invalid = write_baseline("invalid", "not json");
+        assert!(matches!(
+            check_baseline(TEST_RULES, "shiny gold", &invalid),
+            Err(BaselineMismatch::Invalid(_))
+        ));
+
+        for path in &[matching, other_target, mismatching, invalid] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn finds_bag_count_fast() {
+        let graph = Graph::new(&to_bags(to_line_results(ALTERNATE_TEST_RULES)));
+        assert_eq!(126, find_bag_count_fast("shiny gold", &graph));
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(32, find_bag_count_fast("shiny gold", &graph));
+        assert_eq!(33, find_bag_count_fast("bright white", &graph));
+
+        let chain: String = (0..1000)
+            .map(|i| {
+                let count = if i % 100 == 0 { 2 } else { 1 };
+                format!(
+                    "shade{} gold bags contain {} shade{} gold bags.\n",
+                    i,
+                    count,
+                    i + 1
+                )
+            })
+            .chain(iter::once(
+                "shade1000 gold bags contain no other bags.".to_string(),
+            ))
+            .collect();
+        let graph = Graph::new(&parse_rules(&chain).0);
+        assert_eq!(
+            checked_bag_count("shade0 gold", &graph).unwrap(),
+            find_bag_count_fast("shade0 gold", &graph)
+        );
+    }
+
+    #[test]
+    fn lists_index_of_colors() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(
+            "shiny gold → 2 children, 32 total, depth 2",
+            describe("shiny gold", &graph)
+        );
+        let listing = index_listing(&graph);
+        assert_eq!(9, listing.len());
+        assert_eq!("bright white → 1 children, 33 total, depth 3", listing[0]);
+        assert_eq!("dark olive → 2 children, 7 total, depth 1", listing[1]);
+    }
+
+    #[test]
+    fn diffs_reachable_colors() {
+        let mut a = to_bags(to_line_results(TEST_RULES));
+        let b = to_bags(to_line_results(ALTERNATE_TEST_RULES));
+        let (only_a, only_b) = reachable_diff(&a, &b, "shiny gold");
+        assert_eq!(4, only_a.len());
+        assert!(only_a.contains("faded blue"));
+        assert_eq!(6, only_b.len());
+        assert!(only_b.contains("dark violet"));
+
+        let original = a.clone();
+        a.retain(|bag| bag.color != "vibrant plum");
+        a.push(Bag::new_from_rule(
+            "vibrant plum bags contain 1 dark red bag.",
+        ));
+        let (only_a, only_b) = reachable_diff(&a, &original, "shiny gold");
+        assert_eq!(
+            vec!["dark red".to_string()],
+            only_a.into_iter().collect::<Vec<_>>()
+        );
+        assert!(only_b.is_empty());
+    }
+
+    #[test]
+    fn renders_graphml() {
+        let xml = to_graphml(&to_bags(to_line_results(TEST_RULES)));
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml "));
+        assert!(xml.ends_with("</graphml>\n"));
+        assert_eq!(9, xml.matches("<node ").count());
+        assert_eq!(13, xml.matches("<edge ").count());
+        assert!(xml.contains("<node id=\"n0\"><data key=\"label\">bright white</data></node>"));
+        // bright white (n0) holds 1 shiny gold (n7)
+        assert!(xml.contains(
+            "<edge id=\"e0\" source=\"n0\" target=\"n7\"><data key=\"weight\">1</data></edge>"
+        ));
+        for tag in &["graphml", "graph", "node", "edge", "data"] {
+            let opened = xml.matches(&format!("<{} ", tag)).count()
+                + xml.matches(&format!("<{}>", tag)).count();
+            assert_eq!(opened, xml.matches(&format!("</{}>", tag)).count());
+        }
+    }
+
+    #[test]
+    fn escapes_xml() {
+        assert_eq!(
+            "a &amp; &lt;b&gt; &quot;c&quot; &apos;d&apos;",
+            xml_escape(r#"a & <b> "c" 'd'"#)
+        );
+    }
+
+    #[test]
+    fn finds_bag_count_capped() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let mut caps = HashMap::new();
+        assert_eq!(32, find_bag_count_capped("shiny gold", &caps, &graph));
+
+        caps.insert("faded blue".to_string(), 2);
+        assert_eq!(25, find_bag_count_capped("shiny gold", &caps, &graph));
+        caps.insert("shiny gold".to_string(), 0);
+        assert_eq!(25, find_bag_count_capped("shiny gold", &caps, &graph));
+    }
+
+    #[test]
+    fn finds_subtree_leaves() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let expected: HashSet<String> = vec!["faded blue".to_string(), "dotted black".to_string()]
+            .into_iter()
+            .collect();
+        assert_eq!(expected, subtree_leaves("shiny gold", &graph));
+        assert!(subtree_leaves("faded blue", &graph).is_empty());
+    }
+
+    #[test]
+    fn counts_leaf_visits() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let counts = leaf_visit_counts("shiny gold", &graph);
+        assert_eq!(2, counts.len());
+        assert_eq!(13, counts["faded blue"]);
+        assert_eq!(16, counts["dotted black"]);
+        // 1 dark olive and 2 vibrant plum make up the rest of the 32
+        assert_eq!(32 - 3, counts.values().sum::<u64>());
+    }
+
+    #[test]
+    fn builds_graph_from_bag_iterator() {
+        let bags = to_line_results(TEST_RULES)
+            .flatten()
+            .map(|line| Bag::new_from_rule(&line));
+        let graph = Graph::from_bags(bags);
+        let expected = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(
+            expected.ancestors("shiny gold"),
+            graph.ancestors("shiny gold")
+        );
+        assert_eq!(32, find_bag_count_fast("shiny gold", &graph));
+        assert_eq!(
+            canonical_text(&expected.into_bags()),
+            canonical_text(&graph.into_bags())
+        );
+    }
+
+    #[test]
+    fn finds_bag_count_mod() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let graph = Graph::new(&bags);
+        let contains = bags_to_contains_graph(&bags);
+        for &modulus in &[1, 7, 10, 1_000_000_007] {
+            assert_eq!(
+                find_bag_count("shiny gold", &contains) % modulus,
+                find_bag_count_mod("shiny gold", modulus, &graph)
+            );
+        }
+        let deep = indoc!(
+            "
+            a bags contain 4294967296 b bags.
+            b bags contain 4294967296 c bags.
+            c bags contain no other bags."
+        );
+        let graph = Graph::new(&to_bags(to_line_results(deep)));
+        // 2^32 + 2^64 overflows u64, but 2^64 is 582344008 mod 1e9+7
+        assert_eq!(
+            (4_294_967_296 + 582_344_008) % 1_000_000_007,
+            find_bag_count_mod("a", 1_000_000_007, &graph)
+        );
+
+        let wide = indoc!(
+            "
+            a bags contain 9223372036854775808 b bags, 9223372036854775808 c bags.
+            b bags contain no other bags.
+            c bags contain no other bags."
+        );
+        let graph = Graph::new(&to_bags(to_line_results(wide)));
+        // 2^63 + 2^63 = 2^64, which is 2 mod 2^64 - 2
+        assert_eq!(2, find_bag_count_mod("a", u64::MAX - 1, &graph));
+    }
+
+    #[test]
+    fn finds_most_balanced_subtree() {
+        let rules = indoc!(
+            "
+            top bags contain 1 even bag, 2 uneven bags.
+            even bags contain 3 left bags, 3 right bags.
+            uneven bags contain 1 left bag, 5 right bags.
+            left bags contain no other bags.
+            right bags contain no other bags."
+        );
+        let bags = to_bags(to_line_results(rules));
+        assert_eq!(Some("even".to_string()), most_balanced_subtree(&bags));
+
+        let leaves = to_bags(to_line_results("a bags contain no other bags."));
+        assert_eq!(None, most_balanced_subtree(&leaves));
+        let chain = to_bags(to_line_results(ALTERNATE_TEST_RULES));
+        assert_eq!(None, most_balanced_subtree(&chain));
+
+        // dark olive holds 3 and 4, vibrant plum 5 and 6, and bright white's single child
+        // doesn't count
+        let bags = to_bags(to_line_results(TEST_RULES));
+        assert_eq!(Some("dark olive".to_string()), most_balanced_subtree(&bags));
+    }
+
+    #[test]
+    fn finds_depth_from_roots() {
+        let depths = depth_from_roots(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(9, depths.len());
+        assert_eq!(0, depths["light red"]);
+        assert_eq!(0, depths["dark orange"]);
+        // light red -> bright white -> shiny gold
+        assert_eq!(2, depths["shiny gold"]);
+        assert_eq!(3, depths["vibrant plum"]);
+        assert_eq!(4, depths["dotted black"]);
+    }
+
+    #[test]
+    fn renders_dot() {
+        let dot = to_dot(&to_bags(to_line_results(TEST_RULES)));
+        assert!(dot.starts_with("digraph contains {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("    \"muted yellow\" -> \"faded blue\" [label=9];\n"));
+        assert_eq!(13, dot.matches(" -> ").count());
+    }
+
+    #[test]
+    fn finds_minimal_ruleset() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let minimal: Vec<String> = minimal_ruleset_for("shiny gold", &bags)
+            .into_iter()
+            .map(|bag| bag.color)
+            .collect();
+        assert_eq!(
+            vec![
+                "shiny gold",
+                "dark olive",
+                "vibrant plum",
+                "faded blue",
+                "dotted black"
+            ],
+            minimal
+        );
+    }
+
+    #[test]
+    fn renders_subgraph_dot() {
+        let dot = subgraph_dot("shiny gold", &to_bags(to_line_results(TEST_RULES)));
+        assert!(dot.contains("    \"shiny gold\" -> \"dark olive\" [label=1];\n"));
+        assert!(!dot.contains("light red"));
+        assert!(!dot.contains("bright white"));
+        assert_eq!(6, dot.matches(" -> ").count());
+    }
+
+    #[test]
+    fn builds_depth_histogram() {
+        let histogram = depth_histogram(&to_bags(to_line_results(TEST_RULES)));
+        let expected: BTreeMap<u64, usize> = vec![(0, 2), (1, 2), (2, 1), (3, 2), (4, 2)]
+            .into_iter()
+            .collect();
+        assert_eq!(expected, histogram);
+    }
+
+    #[test]
+    fn finds_first_invalid_bags() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let colors = |found: Vec<&Bag>| -> Vec<String> {
+            found.into_iter().map(|bag| bag.color.clone()).collect()
+        };
+        let has_contents = |bag: &Bag| !bag.contents.is_empty();
+        assert_eq!(
+            vec!["faded blue", "dotted black"],
+            colors(first_invalid(&bags, has_contents, 2))
+        );
+        assert_eq!(
+            vec!["faded blue"],
+            colors(first_invalid(&bags, has_contents, 1))
+        );
+        assert!(first_invalid(&bags, |_| true, 5).is_empty());
+    }
+
+    #[test]
+    fn finds_structural_id() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let renamed = rename_colors(TEST_RULES);
+        let renamed_bags: Vec<Bag> = renamed.lines().map(Bag::new_from_rule).collect();
+        assert_eq!(structural_id(&bags), structural_id(&renamed_bags));
+
+        let mut changed = renamed_bags.clone();
+        // the rules are reversed, so the last one is light red's
+        changed[8].contents[0].count += 1;
+        assert_ne!(structural_id(&bags), structural_id(&changed));
+        let alternate = to_bags(to_line_results(ALTERNATE_TEST_RULES));
+        assert_ne!(structural_id(&bags), structural_id(&alternate));
+    }
+
+    #[test]
+    fn finds_mandatory_edges() {
+        let graph = Graph::new(&to_bags(to_line_results(ALTERNATE_TEST_RULES)));
+        let chain = [
+            "shiny gold",
+            "dark red",
+            "dark orange",
+            "dark yellow",
+            "dark green",
+            "dark blue",
+            "dark violet",
+        ];
+        let mut expected: Vec<(String, String)> = chain
+            .windows(2)
+            .map(|pair| (pair[0].to_string(), pair[1].to_string()))
+            .collect();
+        expected.sort();
+        assert_eq!(expected, mandatory_edges("shiny gold", &graph));
+
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert!(mandatory_edges("shiny gold", &graph).is_empty());
+        assert_eq!(
+            vec![("bright white".to_string(), "shiny gold".to_string())],
+            mandatory_edges("bright white", &graph)
+        );
+
+        let rules = indoc!(
+            "
+            a bags contain 1 b bag.
+            b bags contain 1 a bag, 1 c bag.
+            c bags contain no other bags."
+        );
+        let graph = Graph::new(&to_bags(to_line_results(rules)));
+        assert_eq!(
+            vec![
+                ("a".to_string(), "b".to_string()),
+                ("b".to_string(), "c".to_string())
+            ],
+            mandatory_edges("a", &graph)
+        );
+        let rules = indoc!(
+            "
+            a bags contain 1 b bag.
+            b bags contain 1 a bag."
+        );
+        let graph = Graph::new(&to_bags(to_line_results(rules)));
+        assert!(mandatory_edges("a", &graph).is_empty());
+
+        let rules = indoc!(
+            "
+            t bags contain 1 x bag, 1 y bag.
+            x bags contain 1 y bag, 1 m bag.
+            y bags contain 1 x bag.
+            m bags contain no other bags."
+        );
+        let graph = Graph::new(&to_bags(to_line_results(rules)));
+        // t -> y -> x -> m avoids t -> x
+        assert_eq!(
+            vec![("x".to_string(), "m".to_string())],
+            mandatory_edges("t", &graph)
+        );
+    }
+
+    #[test]
+    fn finds_expected_walk_length() {
+        let rules = indoc!(
+            "
+            a bags contain 1 b bag, 3 c bags.
+            b bags contain 2 c bags.
+            c bags contain no other bags."
+        );
+        let graph = Graph::new(&to_bags(to_line_results(rules)));
+        assert_eq!(0.0, expected_walk_length("c", &graph));
+        assert_eq!(1.0, expected_walk_length("b", &graph));
+        // b a quarter of the time, then one more step
+        assert_eq!(1.25, expected_walk_length("a", &graph));
+
+        let graph = Graph::new(&to_bags(to_line_results(ALTERNATE_TEST_RULES)));
+        assert_eq!(6.0, expected_walk_length("shiny gold", &graph));
+    }
+
+    #[test]
+    fn parses_deterministically() {
+        assert!(assert_deterministic(TEST_RULES, 5));
+        assert!(assert_deterministic(ALTERNATE_TEST_RULES, 5));
+        assert!(assert_deterministic("not a rule\n", 3));
+        assert!(assert_deterministic(TEST_RULES, 0));
+    }
+
+    #[test]
+    fn finds_min_cut_to_leaves() {
+        let rules = indoc!(
+            "
+            top bags contain 1 neck bag.
+            neck bags contain 2 left bags, 3 right bags.
+            left bags contain 1 right bag, 4 end bags.
+            right bags contain no other bags.
+            end bags contain no other bags."
+        );
+        let graph = Graph::new(&to_bags(to_line_results(rules)));
+        assert_eq!(
+            vec![("top".to_string(), "neck".to_string())],
+            min_cut_to_leaves("top", &graph)
+        );
+        assert!(min_cut_to_leaves("end", &graph).is_empty());
+
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(
+            vec![
+                ("shiny gold".to_string(), "dark olive".to_string()),
+                ("shiny gold".to_string(), "vibrant plum".to_string()),
+            ],
+            min_cut_to_leaves("shiny gold", &graph)
+        );
+    }
+
+    #[test]
+    fn finds_weight_shares() {
+        let shares = weight_shares(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(9, shares.len());
+        assert!((shares.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!((shares["muted yellow"] - 11.0 / 43.0).abs() < 1e-9);
+        assert_eq!(0.0, shares["faded blue"]);
+    }
+
+    #[test]
+    fn finds_colors_reachable_within_budget() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let reachable = |budget| {
+            let mut colors: Vec<String> = reachable_within_budget("shiny gold", budget, &graph)
+                .into_iter()
+                .collect();
+            colors.sort();
+            colors
+        };
+        assert!(reachable(0).is_empty());
+        assert_eq!(vec!["dark olive", "vibrant plum"], reachable(2));
+        // 1 dark olive then its 3 faded blue, or its 4 dotted black
+        assert_eq!(
+            vec!["dark olive", "faded blue", "vibrant plum"],
+            reachable(4)
+        );
+        assert_eq!(4, reachable(5).len());
+
+        let rules = indoc!(
+            "
+            a bags contain 10 b bags.
+            b bags contain 10 c bags."
+        );
+        let graph = Graph::new(&to_bags(to_line_results(rules)));
+        let expected: HashSet<String> = vec!["b".to_string()].into_iter().collect();
+        assert_eq!(expected, reachable_within_budget("a", 20, &graph));
+        // 10 b and then their 100 c
+        assert_eq!(2, reachable_within_budget("a", 110, &graph).len());
+
+        let rules = indoc!(
+            "
+            a bags contain 3 c bags, 1 x bag.
+            x bags contain 1 y bag.
+            y bags contain 1 z bag.
+            z bags contain 1 c bag.
+            c bags contain 10 d bags."
+        );
+        let graph = Graph::new(&to_bags(to_line_results(rules)));
+        // 3 c are quicker to reach than 1 c through x, y and z, but take 33 bags to reach d
+        // against 14
+        assert!(reachable_within_budget("a", 14, &graph).contains("d"));
+        assert!(!reachable_within_budget("a", 13, &graph).contains("d"));
+    }
+
+    #[test]
+    fn renders_folded_stacks() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let folded = to_folded("shiny gold", &graph);
+        let lines: Vec<&str> = folded.lines().collect();
+        assert_eq!(6, lines.len());
+        assert_eq!("shiny gold;dark olive 1", lines[0]);
+        assert_eq!("shiny gold;dark olive;faded blue 3", lines[1]);
+        assert!(lines.contains(&"shiny gold;vibrant plum;dotted black 12"));
+        let total: u64 = lines
+            .iter()
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<u64>().unwrap())
+            .sum();
+        assert_eq!(32, total);
+    }
+
+    #[test]
+    fn finds_k_shortest_paths() {
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        let paths = k_shortest_paths("shiny gold", "faded blue", 2, &graph);
+        assert_eq!(
+            vec![
+                vec!["shiny gold", "dark olive", "faded blue"],
+                vec!["shiny gold", "vibrant plum", "faded blue"],
+            ],
+            paths
+        );
+        assert_eq!(
+            shortest_path("shiny gold", "faded blue", &graph).unwrap(),
+            paths[0]
+        );
+        assert_eq!(
+            2,
+            k_shortest_paths("shiny gold", "faded blue", 5, &graph).len()
+        );
+        assert!(k_shortest_paths("faded blue", "shiny gold", 2, &graph).is_empty());
+
+        let paths = k_shortest_paths("light red", "faded blue", 10, &graph);
+        assert_eq!(5, paths.len());
+        assert_eq!(vec!["light red", "muted yellow", "faded blue"], paths[0]);
+    }
+
+    #[test]
+    fn expects_bag_count() {
+        assert_eq!(9, expect_bag_count(TEST_RULES, 9).unwrap().len());
+
+        let err = expect_bag_count(TEST_RULES, 8).unwrap_err();
+        assert_eq!(
+            CountMismatch::Count {
+                expected: 8,
+                actual: 9
+            },
+            err
+        );
+        assert_eq!("expected 8 bags, parsed 9", err.to_string());
+        assert_eq!(
+            CountMismatch::Parse(2, ParseError::InvalidRule),
+            expect_bag_count("a bags contain no other bags.\nnot a rule\n", 2).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn finds_longest_repeating_adjective_chain() {
+        let graph = Graph::new(&to_bags(to_line_results(ALTERNATE_TEST_RULES)));
+        assert_eq!(
+            vec![
+                "dark red",
+                "dark orange",
+                "dark yellow",
+                "dark green",
+                "dark blue",
+                "dark violet"
+            ],
+            longest_repeating_adjective_chain("shiny gold", &graph)
+        );
+
+        let graph = Graph::new(&to_bags(to_line_results(TEST_RULES)));
+        assert_eq!(
+            vec!["faded blue"],
+            longest_repeating_adjective_chain("faded blue", &graph)
+        );
+    }
+
+    #[test]
+    fn fingerprints_rulesets() {
+        let bags = to_bags(to_line_results(TEST_RULES));
+        let mut reversed = bags.clone();
+        reversed.reverse();
+        assert_eq!(fingerprint(&bags), fingerprint(&reversed));
+        let alternate = to_bags(to_line_results(ALTERNATE_TEST_RULES));
+        assert_ne!(fingerprint(&bags), fingerprint(&alternate));
+    }
+
+    #[test]
+    fn builds_answer_etags() {
+        let etag = answer_etag(TEST_RULES, "shiny gold");
+        assert_eq!(16, etag.len());
+        assert!(etag.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(etag, answer_etag(TEST_RULES, "shiny gold"));
+        assert_ne!(etag, answer_etag(TEST_RULES, "dark olive"));
+        assert_ne!(etag, answer_etag(ALTERNATE_TEST_RULES, "shiny gold"));
+
+        let garbage = format!("{}\ngarbage", TEST_RULES);
+        assert!(solve(&garbage, "shiny gold").is_err());
+        assert_ne!(etag, answer_etag(&garbage, "shiny gold"));
+    }
+}